@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// Errors reported by this crate. The messages come from SDL where available.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Creating or configuring a window failed.
+    Window(String),
+    /// Reading or writing the clipboard failed.
+    Clipboard(String),
+    /// Creating or setting a mouse cursor failed.
+    Cursor(String),
+    /// Any other error reported by SDL.
+    Sdl(String),
+    /// Setting up or running a painter failed.
+    Painter(String),
+    /// A snapshot did not match or could not be read or written.
+    Snapshot(String),
+    /// A recording could not be read or written, or its replay diverged.
+    Record(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Window(e) => write!(f, "SDL window error: {e}"),
+            Error::Clipboard(e) => write!(f, "SDL clipboard error: {e}"),
+            Error::Cursor(e) => write!(f, "SDL cursor error: {e}"),
+            Error::Sdl(e) => write!(f, "SDL error: {e}"),
+            Error::Painter(e) => write!(f, "painter error: {e}"),
+            Error::Snapshot(e) => write!(f, "snapshot error: {e}"),
+            Error::Record(e) => write!(f, "recording error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Reports a problem the crate recovers from through the `log` facade when the `log` feature
+/// is enabled, and drops it otherwise.
+macro_rules! warn {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        log::warn!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
@@ -0,0 +1,221 @@
+use crate::platform::Platform;
+use crate::EguiSDL2State;
+use sdl2::video::Window;
+use sdl2::TimerSubsystem;
+use std::collections::VecDeque;
+
+/// Number of frame times kept for [`FrameClock::stats`].
+const HISTORY_LEN: usize = 240;
+
+/// Weight of the newest frame time in the smoothed `predicted_dt`.
+const DEFAULT_SMOOTHING: f32 = 0.1;
+
+/// Refresh rate assumed until the first frame was measured, if the display doesn't tell.
+const DEFAULT_REFRESH_RATE: f32 = 60.0;
+
+/// Frame time statistics over the last few seconds, see [`FrameClock::stats`]. Times are in
+/// seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub fps: f32,
+    pub mean: f32,
+    pub min: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+/// The measured frame times, without the clock.
+#[derive(Clone, Debug)]
+struct FrameTimes {
+    smoothing: f32,
+    predicted_dt: f32,
+    history: VecDeque<f32>,
+}
+
+impl FrameTimes {
+    fn new() -> Self {
+        Self {
+            smoothing: DEFAULT_SMOOTHING,
+            predicted_dt: 1.0 / DEFAULT_REFRESH_RATE,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    fn push(&mut self, dt: f32) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(dt);
+        self.predicted_dt = if self.history.len() == 1 {
+            dt
+        } else {
+            self.predicted_dt + (dt - self.predicted_dt) * self.smoothing
+        };
+    }
+
+    fn stats(&self) -> FrameStats {
+        if self.history.is_empty() {
+            return FrameStats::default();
+        }
+        let mut sorted: Vec<f32> = self.history.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |p: f32| {
+            let index = ((sorted.len() - 1) as f32 * p).round() as usize;
+            sorted[index]
+        };
+        let mean = sorted.iter().sum::<f32>() / sorted.len() as f32;
+        FrameStats {
+            fps: if mean > 0.0 { 1.0 / mean } else { 0.0 },
+            mean,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+        }
+    }
+}
+
+/// Measures frame times with SDL's high resolution performance counter and fills in
+/// `RawInput::time` and `RawInput::predicted_dt`.
+///
+/// Call [`FrameClock::tick`] once at the start of every frame, before running egui.
+pub struct FrameClock {
+    timer: TimerSubsystem,
+    frequency: f64,
+    start: u64,
+    last: Option<u64>,
+    times: FrameTimes,
+}
+
+impl FrameClock {
+    pub fn new(timer: TimerSubsystem) -> Self {
+        let frequency = timer.performance_frequency() as f64;
+        let start = timer.performance_counter();
+        Self {
+            timer,
+            frequency,
+            start,
+            last: None,
+            times: FrameTimes::new(),
+        }
+    }
+
+    /// Uses the refresh rate of the window's display as `predicted_dt` until frames have been
+    /// measured.
+    pub fn with_refresh_rate_of(mut self, window: &Window) -> Self {
+        if let Ok(mode) = window.display_mode() {
+            if mode.refresh_rate > 0 {
+                self.times.predicted_dt = 1.0 / mode.refresh_rate as f32;
+            }
+        }
+        self
+    }
+
+    /// Weight of the newest frame time in the smoothed `predicted_dt`, between `0.0`
+    /// (never changes) and `1.0` (no smoothing).
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.times.smoothing = smoothing.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Starts a new frame: measures the time since the previous call and writes the running
+    /// time and predicted frame time into `state`.
+    pub fn tick(&mut self, state: &mut EguiSDL2State) {
+        let now = self.timer.performance_counter();
+        if let Some(last) = self.last {
+            let dt = (now.saturating_sub(last) as f64 / self.frequency) as f32;
+            self.times.push(dt);
+        }
+        self.last = Some(now);
+        state.update_time(Some(self.time()), self.times.predicted_dt);
+    }
+
+    /// Like [`EguiSDL2State::wait_for_event`], but the time spent blocked is left out of the
+    /// frame time, so an idle period doesn't show up as one long frame in `predicted_dt` and
+    /// [`FrameClock::stats`].
+    pub fn wait_for_event(
+        &mut self,
+        state: &EguiSDL2State,
+        window: &(impl Platform + ?Sized),
+        event_pump: &mut sdl2::EventPump,
+    ) -> Option<sdl2::event::Event> {
+        let before = self.timer.performance_counter();
+        let event = state.wait_for_event(window, event_pump);
+        let waited = self.timer.performance_counter().saturating_sub(before);
+        if let Some(last) = &mut self.last {
+            *last = last.saturating_add(waited);
+        }
+        event
+    }
+
+    /// Seconds since the clock was created.
+    pub fn time(&self) -> f64 {
+        self.timer.performance_counter().saturating_sub(self.start) as f64 / self.frequency
+    }
+
+    /// The smoothed frame time in seconds that is passed to egui as `predicted_dt`.
+    pub fn predicted_dt(&self) -> f32 {
+        self.times.predicted_dt
+    }
+
+    /// Duration of the last complete frame in seconds.
+    pub fn last_frame_time(&self) -> Option<f32> {
+        self.times.history.back().copied()
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.times.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_fixed_frame_times() {
+        let mut times = FrameTimes::new();
+        assert_eq!(times.stats(), FrameStats::default());
+        // 100 frames of 10 ms to 1 s:
+        for ms in 1..=100 {
+            times.push(ms as f32 / 100.0);
+        }
+        let stats = times.stats();
+        assert_eq!(stats.min, 0.01);
+        assert_eq!(stats.max, 1.0);
+        assert_eq!(stats.p50, 0.51);
+        assert_eq!(stats.p95, 0.95);
+        assert_eq!(stats.p99, 0.99);
+        assert!((stats.mean - 0.505).abs() < 1e-5);
+        assert!((stats.fps - 1.0 / 0.505).abs() < 1e-3);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut times = FrameTimes::new();
+        for _ in 0..HISTORY_LEN {
+            times.push(1.0);
+        }
+        for _ in 0..HISTORY_LEN {
+            times.push(0.5);
+        }
+        assert_eq!(times.history.len(), HISTORY_LEN);
+        assert_eq!(times.stats().max, 0.5);
+    }
+
+    #[test]
+    fn predicted_dt_is_smoothed() {
+        let mut times = FrameTimes::new();
+        times.smoothing = 0.5;
+        // the first measurement replaces the guess:
+        times.push(0.1);
+        assert_eq!(times.predicted_dt, 0.1);
+        times.push(0.2);
+        assert!((times.predicted_dt - 0.15).abs() < 1e-6);
+        times.push(0.2);
+        assert!((times.predicted_dt - 0.175).abs() < 1e-6);
+    }
+}
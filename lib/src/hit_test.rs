@@ -0,0 +1,318 @@
+use crate::platform::Platform;
+use crate::{Error, Result};
+use egui::{Pos2, Rect, ResizeDirection};
+use sdl2::sys::{SDL_HitTestResult, SDL_Point, SDL_Window};
+use sdl2::video::Window;
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+
+/// Regions of a borderless window that SDL should treat as title bar or resize border.
+///
+/// All rects are in egui points. The regions are usually rebuilt every frame from the UI code,
+/// e.g. by calling [`WindowHitTest::add_drag_region`] with the rect of a custom title bar.
+#[derive(Debug, Default)]
+struct HitTestRegions {
+    drag_regions: Vec<Rect>,
+    no_drag_regions: Vec<Rect>,
+    resize_border: f32,
+    pixels_per_point: f32,
+    size: egui::Vec2,
+    /// Windows holding a reference handed to SDL by [`WindowHitTest::install`].
+    installed_on: Vec<u32>,
+}
+
+impl HitTestRegions {
+    fn hit(&self, pos: Pos2) -> SDL_HitTestResult {
+        use SDL_HitTestResult::*;
+        if self.resize_border > 0.0 {
+            let border = self.resize_border;
+            let west = pos.x < border;
+            let east = pos.x >= self.size.x - border;
+            let north = pos.y < border;
+            let south = pos.y >= self.size.y - border;
+            match (north, south, west, east) {
+                (true, _, true, _) => return SDL_HITTEST_RESIZE_TOPLEFT,
+                (true, _, _, true) => return SDL_HITTEST_RESIZE_TOPRIGHT,
+                (_, true, true, _) => return SDL_HITTEST_RESIZE_BOTTOMLEFT,
+                (_, true, _, true) => return SDL_HITTEST_RESIZE_BOTTOMRIGHT,
+                (true, ..) => return SDL_HITTEST_RESIZE_TOP,
+                (_, true, ..) => return SDL_HITTEST_RESIZE_BOTTOM,
+                (_, _, true, _) => return SDL_HITTEST_RESIZE_LEFT,
+                (_, _, _, true) => return SDL_HITTEST_RESIZE_RIGHT,
+                _ => {}
+            }
+        }
+        // widgets inside the title bar (close button etc.) must stay clickable:
+        if self.no_drag_regions.iter().any(|r| r.contains(pos)) {
+            return SDL_HITTEST_NORMAL;
+        }
+        if self.drag_regions.iter().any(|r| r.contains(pos)) {
+            return SDL_HITTEST_DRAGGABLE;
+        }
+        SDL_HITTEST_NORMAL
+    }
+}
+
+/// Native window moving and resizing for borderless windows through `SDL_SetWindowHitTest`.
+///
+/// Clone it freely; all clones share the same regions. Install it once with
+/// [`WindowHitTest::install`] and declare the regions from the UI code every frame.
+#[derive(Clone, Debug)]
+pub struct WindowHitTest {
+    regions: Arc<Mutex<HitTestRegions>>,
+}
+
+impl Default for WindowHitTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowHitTest {
+    pub fn new() -> Self {
+        Self {
+            regions: Arc::new(Mutex::new(HitTestRegions {
+                pixels_per_point: 1.0,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Registers the hit test callback on `window`.
+    ///
+    /// The callback keeps its own reference to the regions, so it stays valid even if this
+    /// value is dropped. Call [`WindowHitTest::uninstall`] to release it. Installing twice on
+    /// the same window does nothing.
+    pub fn install(&self, window: &Window) -> Result<()> {
+        if self.regions().installed_on.contains(&window.id()) {
+            return Ok(());
+        }
+        let data = Arc::into_raw(self.regions.clone()) as *mut c_void;
+        let result =
+            unsafe { sdl2::sys::SDL_SetWindowHitTest(window.raw(), Some(hit_test_callback), data) };
+        if result != 0 {
+            unsafe { drop(Arc::from_raw(data as *const Mutex<HitTestRegions>)) };
+            return Err(Error::Window(sdl2::get_error()));
+        }
+        self.regions().installed_on.push(window.id());
+        Ok(())
+    }
+
+    /// Removes the hit test callback from `window`. Does nothing if this value (or a clone)
+    /// was not installed on it.
+    pub fn uninstall(&self, window: &Window) {
+        let mut regions = self.regions();
+        let Some(index) = regions
+            .installed_on
+            .iter()
+            .position(|id| *id == window.id())
+        else {
+            return;
+        };
+        regions.installed_on.swap_remove(index);
+        drop(regions);
+        unsafe {
+            sdl2::sys::SDL_SetWindowHitTest(window.raw(), None, std::ptr::null_mut());
+            // release the reference handed to SDL in `install`, SDL no longer uses it:
+            Arc::decrement_strong_count(Arc::as_ptr(&self.regions));
+        }
+    }
+
+    /// Forgets all drag regions. Call at the start of each frame before declaring new ones.
+    pub fn clear(&self) {
+        let mut regions = self.regions();
+        regions.drag_regions.clear();
+        regions.no_drag_regions.clear();
+    }
+
+    /// Marks `rect` (in points) as a region that moves the window when dragged, e.g. a title bar.
+    pub fn add_drag_region(&self, rect: Rect) {
+        self.regions().drag_regions.push(rect);
+    }
+
+    /// Excludes `rect` (in points) from the drag regions, e.g. buttons inside the title bar.
+    pub fn add_no_drag_region(&self, rect: Rect) {
+        self.regions().no_drag_regions.push(rect);
+    }
+
+    /// Width of the resize border along the window edges in points. `0.0` disables it.
+    pub fn set_resize_border(&self, width: f32) {
+        self.regions().resize_border = width;
+    }
+
+    /// Keeps the hit test in sync with the window size and scaling, see
+    /// [`crate::EguiSDL2State::update_hit_test`].
+    pub fn update(&self, size_in_points: egui::Vec2, pixels_per_point: f32) {
+        let mut regions = self.regions();
+        regions.size = size_in_points;
+        regions.pixels_per_point = pixels_per_point;
+    }
+
+    fn regions(&self) -> std::sync::MutexGuard<'_, HitTestRegions> {
+        self.regions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+unsafe extern "C" fn hit_test_callback(
+    _win: *mut SDL_Window,
+    area: *const SDL_Point,
+    data: *mut c_void,
+) -> SDL_HitTestResult {
+    let regions = &*(data as *const Mutex<HitTestRegions>);
+    let Ok(regions) = regions.lock() else {
+        return SDL_HitTestResult::SDL_HITTEST_NORMAL;
+    };
+    let area = &*area;
+    let pos = egui::pos2(
+        area.x as f32 / regions.pixels_per_point,
+        area.y as f32 / regions.pixels_per_point,
+    );
+    regions.hit(pos)
+}
+
+/// An in-progress window move or resize started by `ViewportCommand::StartDrag` or
+/// `ViewportCommand::BeginResize`.
+///
+/// SDL can't hand an already pressed mouse button over to the window manager, so the
+/// window is moved manually from the global mouse position until the button is released.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WindowDrag {
+    direction: Option<ResizeDirection>,
+    start_mouse: (i32, i32),
+    start_position: (i32, i32),
+    start_size: (u32, u32),
+}
+
+impl WindowDrag {
+    pub(crate) fn start(
+        window: &(impl Platform + ?Sized),
+        direction: Option<ResizeDirection>,
+    ) -> Self {
+        let (start_mouse, _) = window.global_mouse_state();
+        Self {
+            direction,
+            start_mouse,
+            start_position: window.window_position(),
+            start_size: window.window_size(),
+        }
+    }
+
+    /// Moves or resizes the window to follow the mouse. Returns `false` once the drag is over.
+    pub(crate) fn update(&self, window: &mut (impl Platform + ?Sized)) -> bool {
+        let ((x, y), left_down) = window.global_mouse_state();
+        if !left_down {
+            return false;
+        }
+        let (dx, dy) = (x - self.start_mouse.0, y - self.start_mouse.1);
+        let (mut left, mut top) = self.start_position;
+        let (mut width, mut height) = (self.start_size.0 as i32, self.start_size.1 as i32);
+        let (min_width, min_height) = window.minimum_window_size();
+        let (min_width, min_height) = (min_width.max(1) as i32, min_height.max(1) as i32);
+
+        let Some(direction) = self.direction else {
+            window.set_window_position(left + dx, top + dy);
+            return true;
+        };
+
+        use ResizeDirection::*;
+        if matches!(direction, East | NorthEast | SouthEast) {
+            width = (width + dx).max(min_width);
+        }
+        if matches!(direction, West | NorthWest | SouthWest) {
+            let new_width = (width - dx).max(min_width);
+            left += width - new_width;
+            width = new_width;
+        }
+        if matches!(direction, South | SouthEast | SouthWest) {
+            height = (height + dy).max(min_height);
+        }
+        if matches!(direction, North | NorthEast | NorthWest) {
+            let new_height = (height - dy).max(min_height);
+            top += height - new_height;
+            height = new_height;
+        }
+        if let Err(e) = window.set_window_size(width as u32, height as u32) {
+            warn!("Unable to resize the window: {e}");
+        }
+        window.set_window_position(left, top);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+    use SDL_HitTestResult::*;
+
+    /// A 200x100 point window with a 4 point border and a title bar with a close button.
+    fn hit_test() -> WindowHitTest {
+        let hit_test = WindowHitTest::new();
+        hit_test.update(egui::vec2(200.0, 100.0), 2.0);
+        hit_test.set_resize_border(4.0);
+        hit_test.add_drag_region(Rect::from_min_max(pos2(0.0, 0.0), pos2(200.0, 20.0)));
+        hit_test.add_no_drag_region(Rect::from_min_max(pos2(180.0, 0.0), pos2(200.0, 20.0)));
+        hit_test
+    }
+
+    #[test]
+    fn regions() {
+        let hit_test = hit_test();
+        let regions = hit_test.regions();
+        let cases = [
+            (pos2(100.0, 50.0), SDL_HITTEST_NORMAL),
+            (pos2(100.0, 10.0), SDL_HITTEST_DRAGGABLE),
+            (pos2(190.0, 10.0), SDL_HITTEST_NORMAL),
+            (pos2(100.0, 2.0), SDL_HITTEST_RESIZE_TOP),
+            (pos2(100.0, 97.0), SDL_HITTEST_RESIZE_BOTTOM),
+            (pos2(2.0, 50.0), SDL_HITTEST_RESIZE_LEFT),
+            (pos2(197.0, 50.0), SDL_HITTEST_RESIZE_RIGHT),
+            // the border wins over the title bar:
+            (pos2(2.0, 10.0), SDL_HITTEST_RESIZE_LEFT),
+        ];
+        for (pos, expected) in cases {
+            assert!(regions.hit(pos) == expected, "{pos:?}");
+        }
+    }
+
+    #[test]
+    fn corners() {
+        let hit_test = hit_test();
+        let regions = hit_test.regions();
+        let cases = [
+            (pos2(0.0, 0.0), SDL_HITTEST_RESIZE_TOPLEFT),
+            (pos2(199.0, 3.9), SDL_HITTEST_RESIZE_TOPRIGHT),
+            (pos2(3.9, 99.0), SDL_HITTEST_RESIZE_BOTTOMLEFT),
+            (pos2(196.0, 96.0), SDL_HITTEST_RESIZE_BOTTOMRIGHT),
+            // just inside of the border:
+            (pos2(4.0, 4.0), SDL_HITTEST_DRAGGABLE),
+            (pos2(195.9, 95.9), SDL_HITTEST_NORMAL),
+        ];
+        for (pos, expected) in cases {
+            assert!(regions.hit(pos) == expected, "{pos:?}");
+        }
+    }
+
+    #[test]
+    fn without_border_or_regions() {
+        let hit_test = hit_test();
+        hit_test.set_resize_border(0.0);
+        hit_test.clear();
+        let regions = hit_test.regions();
+        for pos in [pos2(0.0, 0.0), pos2(100.0, 10.0), pos2(199.0, 99.0)] {
+            assert!(regions.hit(pos) == SDL_HITTEST_NORMAL, "{pos:?}");
+        }
+    }
+
+    #[test]
+    fn callback_converts_pixels_to_points() {
+        let hit_test = hit_test();
+        let data = Arc::as_ptr(&hit_test.regions) as *mut c_void;
+        let hit =
+            |x, y| unsafe { hit_test_callback(std::ptr::null_mut(), &SDL_Point { x, y }, data) };
+        // 2 pixels per point:
+        assert!(hit(200, 20) == SDL_HITTEST_DRAGGABLE);
+        assert!(hit(200, 100) == SDL_HITTEST_NORMAL);
+        assert!(hit(399, 199) == SDL_HITTEST_RESIZE_BOTTOMRIGHT);
+    }
+}
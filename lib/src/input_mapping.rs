@@ -0,0 +1,88 @@
+use egui::emath::RectTransform;
+use egui::{Pos2, Rect};
+
+type MapFn = Box<dyn FnMut(Pos2) -> Option<Pos2>>;
+
+/// Maps window positions to egui positions and back, for egui UIs that are drawn somewhere
+/// other than straight onto the window, e.g. into a texture shown on an in-game monitor or a
+/// 3D surface. See [`EguiSDL2State::set_input_mapping`](crate::EguiSDL2State::set_input_mapping).
+///
+/// Window positions are in points, i.e. SDL window coordinates divided by
+/// [`EguiSDL2State::dpi_scaling`](crate::EguiSDL2State::dpi_scaling).
+pub struct InputMapping {
+    to_egui: MapFn,
+    to_window: Option<MapFn>,
+}
+
+impl InputMapping {
+    /// Maps pointer positions with `to_egui`, which returns `None` for positions outside of the
+    /// UI, e.g. when a ray through the cursor misses the surface egui is drawn on.
+    pub fn new(to_egui: impl FnMut(Pos2) -> Option<Pos2> + 'static) -> Self {
+        Self {
+            to_egui: Box::new(to_egui),
+            to_window: None,
+        }
+    }
+
+    /// Maps egui positions back onto the window, used to place the IME candidate window next
+    /// to the text cursor. Without it the IME position is left alone.
+    pub fn with_inverse(mut self, to_window: impl FnMut(Pos2) -> Option<Pos2> + 'static) -> Self {
+        self.to_window = Some(Box::new(to_window));
+        self
+    }
+
+    /// Shows the egui area `egui_rect` in the window area `window_rect`, e.g. a texture drawn
+    /// at some position and scale. Positions outside of `window_rect` are outside of the UI.
+    pub fn rect(window_rect: Rect, egui_rect: Rect) -> Self {
+        let to_egui = RectTransform::from_to(window_rect, egui_rect);
+        let to_window = to_egui.inverse();
+        Self::new(move |pos| {
+            window_rect
+                .contains(pos)
+                .then(|| to_egui.transform_pos(pos))
+        })
+        .with_inverse(move |pos| Some(to_window.transform_pos(pos)))
+    }
+
+    pub(crate) fn pos_to_egui(&mut self, pos: Pos2) -> Option<Pos2> {
+        (self.to_egui)(pos)
+    }
+
+    /// Maps the corners of `rect`, `None` without an inverse or if a corner is not visible.
+    pub(crate) fn rect_to_window(&mut self, rect: Rect) -> Option<Rect> {
+        let to_window = self.to_window.as_mut()?;
+        Some(Rect::from_two_pos(
+            to_window(rect.min)?,
+            to_window(rect.max)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    #[test]
+    fn rect_maps_both_ways() {
+        let window_rect = Rect::from_min_max(pos2(100.0, 100.0), pos2(200.0, 150.0));
+        let egui_rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(400.0, 200.0));
+        let mut mapping = InputMapping::rect(window_rect, egui_rect);
+        assert_eq!(
+            mapping.pos_to_egui(pos2(150.0, 125.0)),
+            Some(pos2(200.0, 100.0))
+        );
+        assert_eq!(mapping.pos_to_egui(pos2(250.0, 125.0)), None);
+        assert_eq!(
+            mapping.rect_to_window(Rect::from_min_max(pos2(0.0, 0.0), pos2(40.0, 20.0))),
+            Some(Rect::from_min_max(pos2(100.0, 100.0), pos2(110.0, 105.0)))
+        );
+    }
+
+    #[test]
+    fn ime_stays_without_an_inverse() {
+        let mut mapping = InputMapping::new(|pos| Some(pos * 2.0));
+        assert_eq!(mapping.pos_to_egui(pos2(1.0, 2.0)), Some(pos2(2.0, 4.0)));
+        assert_eq!(mapping.rect_to_window(Rect::ZERO), None);
+    }
+}
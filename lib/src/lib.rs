@@ -1,23 +1,64 @@
 #![allow(clippy::redundant_field_names)]
 
-use egui::{Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect};
+#[macro_use]
+mod error;
+mod frame_clock;
+mod hit_test;
+mod input_mapping;
+mod overlay;
+pub mod painter;
+pub mod platform;
+#[cfg(feature = "record")]
+pub mod record;
+mod repaint;
+#[cfg(feature = "runner")]
+pub mod runner;
+pub mod simulate;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod translate;
+pub mod viewport;
+
+pub use error::{Error, Result};
+pub use frame_clock::{FrameClock, FrameStats};
+pub use hit_test::WindowHitTest;
+pub use input_mapping::InputMapping;
+pub use platform::{MockPlatform, Platform};
+pub use translate::{translate_virtual_key_code, TranslationContext};
+
+use egui::{Key, Modifiers, Pos2, RawInput, Rect, ViewportCommand};
+use hit_test::WindowDrag;
+use overlay::Overlay;
+use repaint::RepaintWakeup;
 use sdl2::event::WindowEvent;
-use sdl2::keyboard::{Keycode, Mod};
-use sdl2::mouse::{Cursor, MouseButton, SystemCursor};
-use sdl2::video::Window;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::SystemCursor;
+use std::time::{Duration, Instant};
 
 pub struct FusedCursor {
-    pub cursor: sdl2::mouse::Cursor,
+    /// The SDL cursor currently shown, created on the first cursor change. SDL requires it to
+    /// stay alive while it is set.
+    pub cursor: Option<sdl2::mouse::Cursor>,
     pub icon: sdl2::mouse::SystemCursor,
 }
 
 impl FusedCursor {
     pub fn new() -> Self {
         Self {
-            cursor: sdl2::mouse::Cursor::from_system(sdl2::mouse::SystemCursor::Arrow).unwrap(),
+            cursor: None,
             icon: sdl2::mouse::SystemCursor::Arrow,
         }
     }
+
+    /// Creates and shows the SDL system cursor `icon` right away.
+    pub fn from_system(icon: SystemCursor) -> Result<Self> {
+        let cursor = sdl2::mouse::Cursor::from_system(icon).map_err(Error::Cursor)?;
+        cursor.set();
+        Ok(Self {
+            cursor: Some(cursor),
+            icon,
+        })
+    }
 }
 
 impl Default for FusedCursor {
@@ -26,248 +67,400 @@ impl Default for FusedCursor {
     }
 }
 
-pub fn translate_virtual_key_code(key: Keycode) -> Option<egui::Key> {
-    Some(match key {
-        Keycode::Left => Key::ArrowLeft,
-        Keycode::Up => Key::ArrowUp,
-        Keycode::Right => Key::ArrowRight,
-        Keycode::Down => Key::ArrowDown,
-
-        Keycode::Escape => Key::Escape,
-        Keycode::Tab => Key::Tab,
-        Keycode::Backspace => Key::Backspace,
-        Keycode::Space => Key::Space,
-        Keycode::Return => Key::Enter,
-
-        Keycode::Insert => Key::Insert,
-        Keycode::Home => Key::Home,
-        Keycode::Delete => Key::Delete,
-        Keycode::End => Key::End,
-        Keycode::PageDown => Key::PageDown,
-        Keycode::PageUp => Key::PageUp,
-
-        Keycode::Kp0 | Keycode::Num0 => Key::Num0,
-        Keycode::Kp1 | Keycode::Num1 => Key::Num1,
-        Keycode::Kp2 | Keycode::Num2 => Key::Num2,
-        Keycode::Kp3 | Keycode::Num3 => Key::Num3,
-        Keycode::Kp4 | Keycode::Num4 => Key::Num4,
-        Keycode::Kp5 | Keycode::Num5 => Key::Num5,
-        Keycode::Kp6 | Keycode::Num6 => Key::Num6,
-        Keycode::Kp7 | Keycode::Num7 => Key::Num7,
-        Keycode::Kp8 | Keycode::Num8 => Key::Num8,
-        Keycode::Kp9 | Keycode::Num9 => Key::Num9,
-
-        Keycode::A => Key::A,
-        Keycode::B => Key::B,
-        Keycode::C => Key::C,
-        Keycode::D => Key::D,
-        Keycode::E => Key::E,
-        Keycode::F => Key::F,
-        Keycode::G => Key::G,
-        Keycode::H => Key::H,
-        Keycode::I => Key::I,
-        Keycode::J => Key::J,
-        Keycode::K => Key::K,
-        Keycode::L => Key::L,
-        Keycode::M => Key::M,
-        Keycode::N => Key::N,
-        Keycode::O => Key::O,
-        Keycode::P => Key::P,
-        Keycode::Q => Key::Q,
-        Keycode::R => Key::R,
-        Keycode::S => Key::S,
-        Keycode::T => Key::T,
-        Keycode::U => Key::U,
-        Keycode::V => Key::V,
-        Keycode::W => Key::W,
-        Keycode::X => Key::X,
-        Keycode::Y => Key::Y,
-        Keycode::Z => Key::Z,
-
-        _ => {
-            return None;
-        }
-    })
+/// What egui did with an SDL event, see [`EguiSDL2State::sdl2_input_to_egui`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventResponse {
+    /// egui is using the event, e.g. a click on a panel or typing into a text field, so the
+    /// application should not handle it as well.
+    pub consumed: bool,
+    /// The event changes what egui shows, so a new frame should be run.
+    pub repaint: bool,
 }
 
+/// Which input egui wanted during the last frame, see [`EguiSDL2State::update_input_capture`].
+#[derive(Clone, Copy, Debug, Default)]
+struct InputCapture {
+    wants_pointer_input: bool,
+    wants_keyboard_input: bool,
+    is_pointer_over_area: bool,
+}
+
+/// Hook that sees every SDL event together with the egui events it was translated into, see
+/// [`EguiSDL2State::set_event_filter`].
+pub type EventFilter = Box<dyn FnMut(&sdl2::event::Event, &mut Vec<egui::Event>)>;
+
 pub struct EguiSDL2State {
     pub raw_input: RawInput,
     pub modifiers: Modifiers,
     pub dpi_scaling: f32,
     pub mouse_pointer_position: egui::Pos2,
     pub fused_cursor: FusedCursor,
+    pub hit_test: WindowHitTest,
+    window_drag: Option<WindowDrag>,
+    close_requested: bool,
+    should_close: bool,
+    input_capture: InputCapture,
+    overlay: Option<Overlay>,
+    /// Set when the cursor was left to the application, so the next egui cursor is applied
+    /// even if its icon did not change.
+    cursor_released: bool,
+    event_filter: Option<EventFilter>,
+    /// When egui wants the next frame, `None` if it is idle.
+    repaint_deadline: Option<Instant>,
+    repaint_wakeup: Option<RepaintWakeup>,
+    /// The `user_data` of `ViewportCommand::Screenshot`s that still wait for the frame.
+    screenshot_requests: Vec<egui::UserData>,
+    input_mapping: Option<InputMapping>,
+    /// The pointer is outside of the mapped UI, egui got `PointerGone`.
+    pointer_outside: bool,
+    /// Last pointer position before the [`InputMapping`], in points.
+    window_pointer_pos: Option<Pos2>,
+    /// Last IME position passed to SDL, in window coordinates.
+    ime_rect: Option<sdl2::rect::Rect>,
 }
 
 impl EguiSDL2State {
-    pub fn sdl2_input_to_egui(&mut self, window: &sdl2::video::Window, event: &sdl2::event::Event) {
-        fn sdl_button_to_egui(btn: &MouseButton) -> Option<PointerButton> {
-            match btn {
-                MouseButton::Left => Some(egui::PointerButton::Primary),
-                MouseButton::Middle => Some(egui::PointerButton::Middle),
-                MouseButton::Right => Some(egui::PointerButton::Secondary),
-                _ => None,
-            }
+    /// Translates an SDL event for `window` into egui input.
+    ///
+    /// `window` is usually the [`sdl2::video::Window`], or a [`MockPlatform`] in tests.
+    ///
+    /// The returned [`EventResponse`] is based on what egui wanted during the last frame, so
+    /// call [`EguiSDL2State::update_input_capture`] after every `Context::run`.
+    pub fn sdl2_input_to_egui(
+        &mut self,
+        window: &(impl Platform + ?Sized),
+        event: &sdl2::event::Event,
+    ) -> EventResponse {
+        if let Some(wakeup) = self
+            .repaint_wakeup
+            .as_ref()
+            .filter(|wakeup| wakeup.is_wakeup(event))
+        {
+            let Some(delay) = wakeup.receive(event) else {
+                return EventResponse {
+                    consumed: true,
+                    repaint: false,
+                };
+            };
+            let deadline = Instant::now().checked_add(delay);
+            self.repaint_deadline = match (self.repaint_deadline, deadline) {
+                (Some(current), Some(deadline)) => Some(current.min(deadline)),
+                (current, deadline) => current.or(deadline),
+            };
+            return EventResponse {
+                consumed: true,
+                repaint: delay.is_zero(),
+            };
         }
+        if let Some(response) = self.handle_overlay_event(window, event) {
+            return response;
+        }
+        let events_before = self.raw_input.events.len();
+        let repaint = self.translate_event(window, event);
+        if let Some(filter) = &mut self.event_filter {
+            let mut events = self.raw_input.events.split_off(events_before);
+            filter(event, &mut events);
+            self.raw_input.events.append(&mut events);
+        }
+        let capture = self.input_capture;
 
         use sdl2::event::Event::*;
-        let pixels_per_point = self.dpi_scaling;
-        if event.get_window_id() != Some(window.id()) {
-            return;
+        let consumed = match event {
+            _ if event.get_window_id() != Some(window.window_id()) => false,
+            MouseButtonDown { .. } | MouseWheel { .. } => {
+                capture.wants_pointer_input || capture.is_pointer_over_area
+            }
+            MouseButtonUp { .. } | MouseMotion { .. } => capture.wants_pointer_input,
+            KeyDown { .. } | KeyUp { .. } | TextInput { .. } | TextEditing { .. } => {
+                capture.wants_keyboard_input
+            }
+            _ => false,
+        };
+        EventResponse {
+            consumed,
+            repaint: repaint || self.raw_input.events.len() != events_before,
         }
-        match event {
-            // handle when window Resized and SizeChanged.
-            Window { win_event, .. } => match win_event {
-                WindowEvent::Resized(x, y) | sdl2::event::WindowEvent::SizeChanged(x, y) => {
-                    self.update_screen_rect(*x as u32, *y as u32);
-                }
-                _ => (),
-            },
-            MouseButtonDown { mouse_btn, .. } => {
-                if let Some(pressed) = sdl_button_to_egui(mouse_btn) {
-                    self.raw_input.events.push(egui::Event::PointerButton {
-                        pos: self.mouse_pointer_position,
-                        button: pressed,
-                        pressed: true,
-                        modifiers: self.modifiers,
-                    });
-                }
+    }
+
+    /// Installs a hook that runs for every translated SDL event, before its egui events are
+    /// pushed into [`EguiSDL2State::raw_input`].
+    ///
+    /// The hook may remove, change or add egui events, e.g. to keep `Tab` away from egui during
+    /// gameplay or to turn a gamepad button into `Key::Enter`. It also sees events that egui
+    /// ignores, such as controller events. State changes like resizes are not affected.
+    pub fn set_event_filter(
+        &mut self,
+        filter: impl FnMut(&sdl2::event::Event, &mut Vec<egui::Event>) + 'static,
+    ) {
+        self.event_filter = Some(Box::new(filter));
+    }
+
+    /// Removes the hook installed with [`EguiSDL2State::set_event_filter`].
+    pub fn clear_event_filter(&mut self) {
+        self.event_filter = None;
+    }
+
+    /// Maps pointer positions before they reach egui, and the IME position on its way back, for
+    /// an egui UI rendered into a texture somewhere in the scene. Positions the mapping reports
+    /// as outside of the UI end hovering with `PointerGone`; clicks, scrolling and mouse motion
+    /// there are not passed on, and `MouseMoved` deltas inside are mapped as well. Touches
+    /// arrive as the mouse events SDL synthesizes for them. Events added by an event filter are
+    /// passed on as they are.
+    pub fn set_input_mapping(&mut self, mapping: InputMapping) {
+        self.input_mapping = Some(mapping);
+    }
+
+    /// Removes the mapping installed with [`EguiSDL2State::set_input_mapping`].
+    pub fn clear_input_mapping(&mut self) {
+        self.input_mapping = None;
+        self.pointer_outside = false;
+        self.window_pointer_pos = None;
+    }
+
+    /// Applies the [`InputMapping`] to a translated event. Returns `None` if the event is
+    /// dropped.
+    fn map_event(&mut self, mut event: egui::Event) -> Option<egui::Event> {
+        let Some(mapping) = &mut self.input_mapping else {
+            return Some(event);
+        };
+        match &mut event {
+            egui::Event::PointerMoved(pos) => {
+                self.window_pointer_pos = Some(*pos);
+                let Some(mapped) = mapping.pos_to_egui(*pos) else {
+                    let was_outside = std::mem::replace(&mut self.pointer_outside, true);
+                    return (!was_outside).then_some(egui::Event::PointerGone);
+                };
+                *pos = mapped;
+                self.pointer_outside = false;
             }
-            MouseButtonUp { mouse_btn, .. } => {
-                if let Some(released) = sdl_button_to_egui(mouse_btn) {
-                    self.raw_input.events.push(egui::Event::PointerButton {
-                        pos: self.mouse_pointer_position,
-                        button: released,
-                        pressed: false,
-                        modifiers: self.modifiers,
-                    });
+            egui::Event::MouseMoved(delta) => {
+                if self.pointer_outside {
+                    return None;
                 }
+                // the motion that led to the pointer position, as seen by egui:
+                let pos = self.window_pointer_pos?;
+                *delta = mapping.pos_to_egui(pos)? - mapping.pos_to_egui(pos - *delta)?;
             }
-
-            MouseMotion { x, y, .. } => {
-                self.mouse_pointer_position =
-                    egui::pos2(*x as f32 / pixels_per_point, *y as f32 / pixels_per_point);
-                self.raw_input
-                    .events
-                    .push(egui::Event::PointerMoved(self.mouse_pointer_position));
+            // releases still reach egui, so a drag that left the UI ends:
+            egui::Event::PointerButton { pressed: true, .. }
+            | egui::Event::MouseWheel { .. }
+            | egui::Event::Zoom(_)
+                if self.pointer_outside =>
+            {
+                return None;
             }
+            _ => {}
+        }
+        Some(event)
+    }
 
-            KeyUp {
-                keycode, keymod, ..
-            } => {
-                let key_code = match keycode {
-                    Some(key_code) => key_code,
-                    _ => return,
-                };
-                let key = match translate_virtual_key_code(*key_code) {
-                    Some(key) => key,
-                    _ => return,
-                };
-                self.modifiers = Modifiers {
-                    alt: (*keymod & Mod::LALTMOD == Mod::LALTMOD)
-                        || (*keymod & Mod::RALTMOD == Mod::RALTMOD),
-                    ctrl: (*keymod & Mod::LCTRLMOD == Mod::LCTRLMOD)
-                        || (*keymod & Mod::RCTRLMOD == Mod::RCTRLMOD),
-                    shift: (*keymod & Mod::LSHIFTMOD == Mod::LSHIFTMOD)
-                        || (*keymod & Mod::RSHIFTMOD == Mod::RSHIFTMOD),
-                    mac_cmd: *keymod & Mod::LGUIMOD == Mod::LGUIMOD,
-
-                    //TOD: Test on both windows and mac
-                    command: (*keymod & Mod::LCTRLMOD == Mod::LCTRLMOD)
-                        || (*keymod & Mod::LGUIMOD == Mod::LGUIMOD),
-                };
+    /// Moves the IME candidate window next to egui's text cursor.
+    fn update_ime_rect(&mut self, window: &(impl Platform + ?Sized), cursor_rect: Rect) {
+        let rect = match &mut self.input_mapping {
+            Some(mapping) => mapping.rect_to_window(cursor_rect),
+            None => Some(cursor_rect),
+        };
+        let Some(rect) = rect.map(|rect| rect * self.dpi_scaling) else {
+            return;
+        };
+        let rect = sdl2::rect::Rect::new(
+            rect.min.x.round() as i32,
+            rect.min.y.round() as i32,
+            rect.width().round().max(1.0) as u32,
+            rect.height().round().max(1.0) as u32,
+        );
+        if self.ime_rect != Some(rect) {
+            window.set_text_input_rect(rect);
+            self.ime_rect = Some(rect);
+        }
+    }
+
+    /// Turns on overlay mode: egui only receives input while the overlay is shown, and
+    /// pressing `hotkey` shows or hides it. The overlay starts hidden.
+    ///
+    /// While shown, relative mouse mode and mouse grab are released and restored to the
+    /// game's settings when hidden again. The cursor icon is only changed while the pointer
+    /// is over egui.
+    pub fn enable_overlay(&mut self, hotkey: Keycode) {
+        self.overlay = Some(Overlay::new(hotkey));
+    }
+
+    /// Turns off overlay mode, giving the mouse back to the game if the overlay was shown.
+    pub fn disable_overlay(&mut self, window: &(impl Platform + ?Sized)) {
+        if let Some(mut overlay) = self.overlay.take() {
+            overlay.hide(window);
+        }
+    }
+
+    /// `true` if egui is receiving input, i.e. the overlay is shown or overlay mode is off.
+    pub fn overlay_visible(&self) -> bool {
+        self.overlay.as_ref().is_none_or(Overlay::is_visible)
+    }
+
+    /// Shows or hides the overlay. Does nothing if overlay mode is off.
+    pub fn set_overlay_visible(&mut self, window: &(impl Platform + ?Sized), visible: bool) {
+        let Some(overlay) = &mut self.overlay else {
+            return;
+        };
+        if visible {
+            overlay.show(window);
+        } else if overlay.is_visible() {
+            overlay.hide(window);
+            // egui must not keep hovering whatever was under the cursor:
+            self.raw_input.events.push(egui::Event::PointerGone);
+            self.cursor_released = true;
+        }
+    }
 
-                self.raw_input.events.push(egui::Event::Key {
-                    key: key,
-                    physical_key: None,
-                    pressed: false,
-                    repeat: false,
-                    modifiers: self.modifiers,
+    /// Toggles the overlay on its hotkey and swallows egui input while it is hidden. Returns
+    /// `None` if the event should be translated as usual.
+    fn handle_overlay_event(
+        &mut self,
+        window: &(impl Platform + ?Sized),
+        event: &sdl2::event::Event,
+    ) -> Option<EventResponse> {
+        let overlay = self.overlay.as_ref()?;
+        if let sdl2::event::Event::KeyDown {
+            keycode: Some(keycode),
+            repeat: false,
+            window_id,
+            ..
+        } = event
+        {
+            if *keycode == overlay.hotkey && *window_id == window.window_id() {
+                let visible = !overlay.is_visible();
+                self.set_overlay_visible(window, visible);
+                return Some(EventResponse {
+                    consumed: true,
+                    repaint: true,
                 });
             }
+        }
+        if overlay.is_visible() {
+            return None;
+        }
+        // window state like close requests, resizes and dropped files is still tracked, only
+        // the egui input is dropped:
+        let events_before = self.raw_input.events.len();
+        let repaint = self.translate_event(window, event);
+        self.raw_input.events.truncate(events_before);
+        Some(EventResponse {
+            consumed: false,
+            repaint,
+        })
+    }
 
-            KeyDown {
-                keycode, keymod, ..
-            } => {
-                let key_code = match keycode {
-                    Some(key_code) => key_code,
-                    _ => return,
-                };
+    /// Makes `ctx` push an SDL user event whenever a repaint is requested, also from other
+    /// threads, so a loop blocked in [`EguiSDL2State::wait_for_event`] wakes up.
+    ///
+    /// [`EguiSDL2State::sdl2_input_to_egui`] swallows these events and reports them as
+    /// consumed. This replaces any repaint callback previously set on `ctx`.
+    pub fn enable_repaint_wakeup(
+        &mut self,
+        ctx: &egui::Context,
+        events: &sdl2::EventSubsystem,
+        window: &(impl Platform + ?Sized),
+    ) -> Result<()> {
+        self.repaint_wakeup = Some(RepaintWakeup::install(ctx, events, window.window_id())?);
+        Ok(())
+    }
 
-                let key = match translate_virtual_key_code(*key_code) {
-                    Some(key) => key,
-                    _ => return,
-                };
-                self.modifiers = Modifiers {
-                    alt: (*keymod & Mod::LALTMOD == Mod::LALTMOD)
-                        || (*keymod & Mod::RALTMOD == Mod::RALTMOD),
-                    ctrl: (*keymod & Mod::LCTRLMOD == Mod::LCTRLMOD)
-                        || (*keymod & Mod::RCTRLMOD == Mod::RCTRLMOD),
-                    shift: (*keymod & Mod::LSHIFTMOD == Mod::LSHIFTMOD)
-                        || (*keymod & Mod::RSHIFTMOD == Mod::RSHIFTMOD),
-                    mac_cmd: *keymod & Mod::LGUIMOD == Mod::LGUIMOD,
-
-                    //TOD: Test on both windows and mac
-                    command: (*keymod & Mod::LCTRLMOD == Mod::LCTRLMOD)
-                        || (*keymod & Mod::LGUIMOD == Mod::LGUIMOD),
-                };
+    /// Remembers which input egui wants after a frame, used to fill in
+    /// [`EventResponse::consumed`].
+    pub fn update_input_capture(&mut self, ctx: &egui::Context) {
+        self.input_capture = InputCapture {
+            wants_pointer_input: ctx.wants_pointer_input(),
+            wants_keyboard_input: ctx.wants_keyboard_input(),
+            is_pointer_over_area: ctx.is_pointer_over_area(),
+        };
+    }
 
-                self.raw_input.events.push(egui::Event::Key {
-                    key,
-                    physical_key: None,
-                    pressed: true,
-                    repeat: false,
-                    modifiers: self.modifiers,
+    /// Pushes the egui events for `event`. Returns `true` if the event changed the window
+    /// state without producing an egui event, e.g. a resize.
+    fn translate_event(
+        &mut self,
+        window: &(impl Platform + ?Sized),
+        event: &sdl2::event::Event,
+    ) -> bool {
+        use sdl2::event::Event::*;
+        // `Quit` is not tied to a window, it arrives when the last window was closed:
+        if let Quit { .. } = event {
+            self.request_close();
+            return true;
+        }
+        if event.get_window_id() != Some(window.window_id()) {
+            return false;
+        }
+        match event {
+            Window {
+                win_event: WindowEvent::Close,
+                ..
+            } => {
+                self.request_close();
+                return true;
+            }
+            // handle when window Resized and SizeChanged.
+            Window {
+                win_event: WindowEvent::Resized(x, y) | WindowEvent::SizeChanged(x, y),
+                ..
+            } => {
+                self.update_screen_rect(*x as u32, *y as u32);
+                return true;
+            }
+            // e.g. exposed or focus changes, which need a redraw:
+            Window { .. } => return true,
+            DropFile { filename, .. } => {
+                let path = std::path::PathBuf::from(filename);
+                self.raw_input.dropped_files.push(egui::DroppedFile {
+                    name: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    path: Some(path),
+                    ..Default::default()
                 });
+                return true;
+            }
+            KeyDown { keymod, .. } | KeyUp { keymod, .. } => {
+                self.modifiers = translate::translate_modifiers(*keymod);
+            }
+            _ => {}
+        }
 
-                if self.modifiers.command && key == Key::C {
-                    // println!("copy event");
-                    self.raw_input.events.push(egui::Event::Copy);
-                } else if self.modifiers.command && key == Key::X {
-                    // println!("cut event");
-                    self.raw_input.events.push(egui::Event::Cut);
-                } else if self.modifiers.command && key == Key::V {
-                    // println!("paste");
-                    if let Ok(contents) = window.subsystem().clipboard().clipboard_text() {
+        let modifiers = match event {
+            // modifier keys alone don't reach egui, so ask SDL which ones are held:
+            MouseWheel { .. } => window.modifier_state(),
+            _ => self.modifiers,
+        };
+        let ctx = TranslationContext {
+            window_id: window.window_id(),
+            pixels_per_point: self.dpi_scaling,
+            modifiers,
+            pointer_pos: self.mouse_pointer_position,
+        };
+        for event in translate::translate_event(event, &ctx) {
+            let Some(event) = self.map_event(event) else {
+                continue;
+            };
+            match &event {
+                egui::Event::PointerMoved(pos) => self.mouse_pointer_position = *pos,
+                egui::Event::Key {
+                    key: Key::V,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } if modifiers.command => {
+                    self.raw_input.events.push(event);
+                    if let Some(contents) = window.clipboard_text() {
                         self.raw_input.events.push(egui::Event::Text(contents));
                     }
+                    continue;
                 }
+                _ => {}
             }
-
-            TextInput { text, .. } => {
-                self.raw_input.events.push(egui::Event::Text(text.clone()));
-            }
-            MouseWheel { x, y, .. } => {
-                let delta = egui::vec2(*x as f32 * 8.0, *y as f32 * 8.0);
-                let sdl = window.subsystem().sdl();
-                // zoom:
-                if sdl.keyboard().mod_state() & Mod::LCTRLMOD == Mod::LCTRLMOD
-                    || sdl.keyboard().mod_state() & Mod::RCTRLMOD == Mod::RCTRLMOD
-                {
-                    let zoom_delta = (delta.y / 125.0).exp();
-                    self.raw_input.events.push(egui::Event::Zoom(zoom_delta));
-                }
-                // horizontal scroll:
-                else if sdl.keyboard().mod_state() & Mod::LSHIFTMOD == Mod::LSHIFTMOD
-                    || sdl.keyboard().mod_state() & Mod::RSHIFTMOD == Mod::RSHIFTMOD
-                {
-                    let e = egui::Event::MouseWheel {
-                        unit: MouseWheelUnit::Point,
-                        delta: egui::vec2(delta.x + delta.y, 0.0),
-                        modifiers: Default::default(),
-                    };
-                    self.raw_input.events.push(e);
-                    // regular scroll:
-                } else {
-                    let e = egui::Event::MouseWheel {
-                        unit: MouseWheelUnit::Point,
-                        delta: egui::vec2(delta.x, delta.y),
-                        modifiers: Default::default(),
-                    };
-                    self.raw_input.events.push(e)
-                }
-            }
-            _ => {}
+            self.raw_input.events.push(event);
         }
+        false
     }
 
     pub fn update_screen_rect(&mut self, width: u32, height: u32) {
@@ -296,28 +489,254 @@ impl EguiSDL2State {
             dpi_scaling,
             mouse_pointer_position: egui::Pos2::new(0.0, 0.0),
             fused_cursor: FusedCursor::new(),
+            hit_test: WindowHitTest::new(),
+            window_drag: None,
+            close_requested: false,
+            should_close: false,
+            input_capture: InputCapture::default(),
+            overlay: None,
+            cursor_released: false,
+            event_filter: None,
+            repaint_deadline: Some(Instant::now()),
+            repaint_wakeup: None,
+            screenshot_requests: Vec::new(),
+            input_mapping: None,
+            pointer_outside: false,
+            window_pointer_pos: None,
+            ime_rect: None,
         }
     }
 
-    pub fn process_output(&mut self, window: &Window, egui_output: &egui::PlatformOutput) {
+    pub fn process_output(
+        &mut self,
+        window: &(impl Platform + ?Sized),
+        egui_output: &egui::PlatformOutput,
+    ) {
         for command in &egui_output.commands {
+            if let egui::OutputCommand::CopyText(copied_text) = command {
+                if let Err(e) = window.set_clipboard_text(copied_text) {
+                    warn!("Unable to set clipboard content to SDL clipboard: {e}");
+                }
+            }
+        }
+        if let Some(ime) = &egui_output.ime {
+            self.update_ime_rect(window, ime.cursor_rect);
+        }
+        let pointer_over_egui =
+            self.input_capture.is_pointer_over_area || self.input_capture.wants_pointer_input;
+        if self.overlay.is_some() && !(self.overlay_visible() && pointer_over_egui) {
+            // the cursor belongs to the game:
+            self.cursor_released = true;
+            return;
+        }
+        let force = std::mem::take(&mut self.cursor_released);
+        EguiSDL2State::translate_cursor(
+            window,
+            &mut self.fused_cursor,
+            egui_output.cursor_icon,
+            force,
+        );
+    }
+
+    /// Applies the viewport commands of a frame, e.g.
+    /// `full_output.viewport_output[&egui::ViewportId::ROOT].commands`, to the window.
+    /// See [`viewport::apply_viewport_command`] for the supported window commands.
+    ///
+    /// Call this every frame: window drags started by `StartDrag` and `BeginResize` are
+    /// advanced here until the mouse button is released.
+    pub fn process_viewport_commands(
+        &mut self,
+        window: &mut (impl Platform + ?Sized),
+        commands: &[ViewportCommand],
+    ) {
+        for command in commands {
             match command {
-                egui::OutputCommand::CopyText(copied_text) => {
-                    let result = window
-                        .subsystem()
-                        .clipboard()
-                        .set_clipboard_text(&copied_text);
-                    if result.is_err() {
-                        dbg!("Unable to set clipboard content to SDL clipboard.");
+                ViewportCommand::StartDrag => {
+                    self.window_drag = Some(WindowDrag::start(window, None));
+                }
+                ViewportCommand::BeginResize(direction) => {
+                    self.window_drag = Some(WindowDrag::start(window, Some(*direction)));
+                }
+                ViewportCommand::Close => {
+                    self.should_close = true;
+                }
+                ViewportCommand::CancelClose => {
+                    self.close_requested = false;
+                }
+                ViewportCommand::RequestCut => self.raw_input.events.push(egui::Event::Cut),
+                ViewportCommand::RequestCopy => self.raw_input.events.push(egui::Event::Copy),
+                ViewportCommand::RequestPaste => {
+                    if let Some(contents) = window.clipboard_text() {
+                        self.raw_input.events.push(egui::Event::Paste(contents));
                     }
                 }
-                _ => {}
+                ViewportCommand::IMEAllowed(allowed) => window.set_text_input(*allowed),
+                ViewportCommand::IMERect(rect) => self.update_ime_rect(&*window, *rect),
+                ViewportCommand::Screenshot(user_data) => {
+                    self.screenshot_requests.push(user_data.clone());
+                }
+                ViewportCommand::Title(title) => {
+                    let viewport_id = self.raw_input.viewport_id;
+                    let viewport = self.raw_input.viewports.entry(viewport_id).or_default();
+                    viewport.title = Some(title.clone());
+                    if let Err(e) = window.apply_viewport_command(command, self.dpi_scaling) {
+                        warn!("Unable to set the window title: {e}");
+                    }
+                }
+                _ => {
+                    if let Err(e) = window.apply_viewport_command(command, self.dpi_scaling) {
+                        warn!("Unable to apply {command:?}: {e}");
+                    }
+                }
+            }
+        }
+        // a close request that egui did not cancel during this frame closes the window:
+        if std::mem::take(&mut self.close_requested) {
+            self.should_close = true;
+        }
+        if let Some(drag) = &self.window_drag {
+            if !drag.update(window) {
+                self.window_drag = None;
             }
         }
-        EguiSDL2State::translate_cursor(&mut self.fused_cursor, egui_output.cursor_icon);
+        self.update_hit_test(window);
     }
 
-    fn translate_cursor(fused: &mut FusedCursor, cursor_icon: egui::CursorIcon) {
+    /// Paints a frame with `painter`. If egui asked for a screenshot with
+    /// `ViewportCommand::Screenshot`, the frame is read back and handed to egui as
+    /// `egui::Event::Screenshot` in the next frame's input.
+    ///
+    /// Call after [`EguiSDL2State::process_viewport_commands`], in place of
+    /// [`Painter::paint`](painter::Painter::paint).
+    pub fn paint(
+        &mut self,
+        painter: &mut (impl painter::Painter + ?Sized),
+        pixels_per_point: f32,
+        textures_delta: &egui::TexturesDelta,
+        clipped_primitives: &[egui::ClippedPrimitive],
+    ) -> Result<()> {
+        if self.screenshot_requests.is_empty() {
+            return painter.paint(pixels_per_point, textures_delta, clipped_primitives);
+        }
+        if !painter.can_capture() {
+            warn!("The painter can't capture screenshots");
+            self.screenshot_requests.clear();
+            return painter.paint(pixels_per_point, textures_delta, clipped_primitives);
+        }
+        match painter.paint_and_capture(pixels_per_point, textures_delta, clipped_primitives)? {
+            Some(image) => self.deliver_screenshot(image),
+            // the frame was skipped, e.g. during a resize, try again with the next one:
+            None => self.repaint_deadline = Some(Instant::now()),
+        }
+        Ok(())
+    }
+
+    /// `true` while egui waits for a screenshot, see [`EguiSDL2State::deliver_screenshot`].
+    pub fn wants_screenshot(&self) -> bool {
+        !self.screenshot_requests.is_empty()
+    }
+
+    /// Answers all pending `ViewportCommand::Screenshot`s with `image`, for applications that
+    /// capture frames themselves instead of using [`EguiSDL2State::paint`]. egui is asked to
+    /// run again right away to receive it.
+    pub fn deliver_screenshot(&mut self, image: egui::ColorImage) {
+        if self.screenshot_requests.is_empty() {
+            return;
+        }
+        let image = std::sync::Arc::new(image);
+        let viewport_id = self.raw_input.viewport_id;
+        for user_data in self.screenshot_requests.drain(..) {
+            self.raw_input.events.push(egui::Event::Screenshot {
+                viewport_id,
+                user_data,
+                image: image.clone(),
+            });
+        }
+        self.repaint_deadline = Some(Instant::now());
+    }
+
+    /// Remembers when egui wants to be run again, from the `repaint_delay` of the frame's
+    /// viewports. Call after every `Context::run`.
+    pub fn update_repaint_delay(&mut self, full_output: &egui::FullOutput) {
+        let delay = full_output
+            .viewport_output
+            .values()
+            .map(|output| output.repaint_delay)
+            .min()
+            .unwrap_or(Duration::MAX);
+        self.set_repaint_delay(delay);
+    }
+
+    pub(crate) fn set_repaint_delay(&mut self, delay: Duration) {
+        self.repaint_deadline = Instant::now().checked_add(delay);
+    }
+
+    /// How long the application may block before egui needs the next frame. `None` means egui
+    /// is idle and only new input requires a frame.
+    pub fn repaint_delay(&self) -> Option<Duration> {
+        self.repaint_deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Blocks until an SDL event arrives or egui wants the next frame, whichever comes
+    /// first. Returns the event, if any; drain the rest with `EventPump::poll_iter`.
+    ///
+    /// While `window` is minimized nothing is drawn, so this waits for the next event only.
+    ///
+    /// With a [`FrameClock`], use [`FrameClock::wait_for_event`] to keep the wait out of the
+    /// measured frame times.
+    pub fn wait_for_event(
+        &self,
+        window: &(impl Platform + ?Sized),
+        event_pump: &mut sdl2::EventPump,
+    ) -> Option<sdl2::event::Event> {
+        let delay = if window.is_minimized() {
+            None
+        } else {
+            self.repaint_delay()
+        };
+        match delay {
+            None => Some(event_pump.wait_event()),
+            Some(delay) if delay.is_zero() => event_pump.poll_event(),
+            Some(delay) => match u32::try_from(delay.as_millis().max(1)) {
+                Ok(timeout) => event_pump.wait_event_timeout(timeout),
+                Err(_) => Some(event_pump.wait_event()),
+            },
+        }
+    }
+
+    /// `true` once the window was asked to close and egui did not answer with
+    /// `ViewportCommand::CancelClose`, or egui sent `ViewportCommand::Close` itself.
+    ///
+    /// Check this after [`EguiSDL2State::process_viewport_commands`] to leave the main loop.
+    pub fn should_close(&self) -> bool {
+        self.should_close
+    }
+
+    /// Lets egui know that the user wants to close the window, so the next frame sees
+    /// `ctx.input(|i| i.viewport().close_requested())`.
+    pub fn request_close(&mut self) {
+        let viewport_id = self.raw_input.viewport_id;
+        let viewport = self.raw_input.viewports.entry(viewport_id).or_default();
+        if !viewport.events.contains(&egui::ViewportEvent::Close) {
+            viewport.events.push(egui::ViewportEvent::Close);
+        }
+        self.close_requested = true;
+    }
+
+    /// Passes the current window size and scaling on to [`EguiSDL2State::hit_test`].
+    pub fn update_hit_test(&self, window: &(impl Platform + ?Sized)) {
+        let (width, height) = window.window_size();
+        let size = egui::vec2(width as f32, height as f32) / self.dpi_scaling;
+        self.hit_test.update(size, self.dpi_scaling);
+    }
+
+    fn translate_cursor(
+        window: &(impl Platform + ?Sized),
+        fused: &mut FusedCursor,
+        cursor_icon: egui::CursorIcon,
+        force: bool,
+    ) {
         let tmp_icon = match cursor_icon {
             egui::CursorIcon::Crosshair => SystemCursor::Crosshair,
             egui::CursorIcon::Default => SystemCursor::Arrow,
@@ -336,10 +755,252 @@ impl EguiSDL2State {
             _ => SystemCursor::Arrow,
         };
 
-        if tmp_icon != fused.icon {
-            fused.cursor = Cursor::from_system(tmp_icon).unwrap();
+        if tmp_icon != fused.icon || force {
+            match window.set_system_cursor(tmp_icon) {
+                Ok(cursor) => fused.cursor = cursor,
+                Err(e) => warn!("Unable to set the mouse cursor: {e}"),
+            }
+            // don't retry a failing cursor every frame:
             fused.icon = tmp_icon;
-            fused.cursor.set();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::InputSimulator;
+    use sdl2::keyboard::Mod;
+    use sdl2::mouse::SystemCursor;
+
+    fn setup() -> (EguiSDL2State, MockPlatform, InputSimulator) {
+        let platform = MockPlatform::new(1, 800, 600);
+        let state = EguiSDL2State::new(800, 600, 2.0);
+        let input = InputSimulator::new(platform.window_id, 2.0);
+        (state, platform, input)
+    }
+
+    #[test]
+    fn click_reaches_egui_in_points() {
+        let (mut state, platform, mut input) = setup();
+        simulate::feed(&mut state, &platform, input.click(egui::pos2(10.0, 20.0)));
+        let events = state.raw_input.take().events;
+        assert!(events.contains(&egui::Event::PointerMoved(egui::pos2(10.0, 20.0))));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            egui::Event::PointerButton {
+                pos,
+                pressed: true,
+                ..
+            } if *pos == egui::pos2(10.0, 20.0)
+        )));
+    }
+
+    #[test]
+    fn paste_reads_the_platform_clipboard() {
+        let (mut state, platform, mut input) = setup();
+        *platform.clipboard.borrow_mut() = Some("pasted".to_owned());
+        let command = if cfg!(target_os = "macos") {
+            Mod::LGUIMOD
+        } else {
+            Mod::LCTRLMOD
+        };
+        simulate::feed(&mut state, &platform, input.shortcut(command, Keycode::V));
+        let events = state.raw_input.take().events;
+        assert!(events.contains(&egui::Event::Text("pasted".to_owned())));
+    }
+
+    #[test]
+    fn window_state_changes() {
+        let (mut state, platform, _) = setup();
+        let resize = sdl2::event::Event::Window {
+            timestamp: 0,
+            window_id: platform.window_id,
+            win_event: WindowEvent::Resized(400, 200),
+        };
+        assert!(state.sdl2_input_to_egui(&platform, &resize).repaint);
+        assert_eq!(
+            state.raw_input.screen_rect,
+            Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(200.0, 100.0)))
+        );
+
+        state.sdl2_input_to_egui(&platform, &sdl2::event::Event::Quit { timestamp: 0 });
+        let viewport = &state.raw_input.viewports[&state.raw_input.viewport_id];
+        assert!(viewport.events.contains(&egui::ViewportEvent::Close));
+    }
+
+    #[test]
+    fn output_is_applied_to_the_platform() {
+        let (mut state, platform, _) = setup();
+        let output = egui::PlatformOutput {
+            commands: vec![egui::OutputCommand::CopyText("copied".to_owned())],
+            cursor_icon: egui::CursorIcon::Text,
+            ime: Some(egui::output::IMEOutput {
+                rect: Rect::from_min_size(egui::pos2(10.0, 10.0), egui::vec2(100.0, 20.0)),
+                cursor_rect: Rect::from_min_size(egui::pos2(30.0, 10.0), egui::vec2(1.0, 20.0)),
+            }),
+            ..Default::default()
+        };
+        state.process_output(&platform, &output);
+        assert_eq!(platform.clipboard.borrow().as_deref(), Some("copied"));
+        assert_eq!(platform.cursor.get(), Some(SystemCursor::IBeam));
+        assert_eq!(
+            platform.text_input_rect.get(),
+            Some(sdl2::rect::Rect::new(60, 20, 2, 40))
+        );
+    }
+
+    #[test]
+    fn close_can_be_cancelled_once() {
+        let (mut state, mut platform, _) = setup();
+        state.request_close();
+        state.process_viewport_commands(&mut platform, &[ViewportCommand::CancelClose]);
+        assert!(!state.should_close());
+
+        state.request_close();
+        state.process_viewport_commands(&mut platform, &[]);
+        assert!(state.should_close());
+
+        let (mut state, mut platform, _) = setup();
+        state.process_viewport_commands(&mut platform, &[ViewportCommand::Close]);
+        assert!(state.should_close());
+    }
+
+    #[test]
+    fn window_commands_reach_the_platform() {
+        let (mut state, mut platform, _) = setup();
+        let commands = [
+            ViewportCommand::Title("title".to_owned()),
+            ViewportCommand::InnerSize(egui::vec2(100.0, 50.0)),
+        ];
+        state.process_viewport_commands(&mut platform, &commands);
+        assert_eq!(*platform.viewport_commands.borrow(), commands);
+        let viewport = &state.raw_input.viewports[&state.raw_input.viewport_id];
+        assert_eq!(viewport.title.as_deref(), Some("title"));
+    }
+
+    #[test]
+    fn drag_follows_the_mouse_until_released() {
+        let (mut state, mut platform, _) = setup();
+        platform.window_position.set((100, 100));
+        platform.global_mouse.set(((110, 110), true));
+        state.process_viewport_commands(&mut platform, &[ViewportCommand::StartDrag]);
+
+        platform.global_mouse.set(((130, 125), true));
+        state.process_viewport_commands(&mut platform, &[]);
+        assert_eq!(platform.window_position.get(), (120, 115));
+
+        platform.global_mouse.set(((150, 150), false));
+        state.process_viewport_commands(&mut platform, &[]);
+        assert_eq!(platform.window_position.get(), (120, 115));
+    }
+
+    #[test]
+    fn input_mapping_hides_the_ui_outside_of_it() {
+        let (mut state, platform, mut input) = setup();
+        state.set_input_mapping(InputMapping::rect(
+            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(128.0, 128.0)),
+            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(512.0, 512.0)),
+        ));
+        simulate::feed(
+            &mut state,
+            &platform,
+            input.mouse_move(egui::pos2(64.0, 32.0)),
+        );
+        simulate::feed(
+            &mut state,
+            &platform,
+            input.mouse_move(egui::pos2(96.0, 32.0)),
+        );
+        let events = state.raw_input.take().events;
+        assert!(events.contains(&egui::Event::PointerMoved(egui::pos2(384.0, 128.0))));
+        assert!(events.contains(&egui::Event::MouseMoved(egui::vec2(128.0, 0.0))));
+
+        // a drag that leaves the UI is released in egui, and motion outside is dropped:
+        let drag = input.drag(&[egui::pos2(96.0, 32.0), egui::pos2(200.0, 32.0)]);
+        simulate::feed(&mut state, &platform, drag);
+        let events = state.raw_input.take().events;
+        let buttons: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                egui::Event::PointerButton { pressed, .. } => Some(*pressed),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(buttons, [true, false]);
+        let gone = events
+            .iter()
+            .position(|event| *event == egui::Event::PointerGone);
+        let outside = &events[gone.expect("no PointerGone")..];
+        assert!(!outside
+            .iter()
+            .any(|event| matches!(event, egui::Event::MouseMoved(_))));
+
+        // clicks outside don't press anything:
+        simulate::feed(&mut state, &platform, input.click(egui::pos2(200.0, 40.0)));
+        let events = state.raw_input.take().events;
+        assert!(!events.iter().any(|event| matches!(
+            event,
+            egui::Event::PointerButton { pressed: true, .. } | egui::Event::PointerGone
+        )));
+    }
+
+    #[test]
+    fn overlay_restores_the_game_mouse() {
+        let (mut state, platform, mut input) = setup();
+        platform.relative_mouse_mode.set(true);
+        platform.mouse_grab.set(true);
+        platform.cursor_visible.set(false);
+        state.enable_overlay(Keycode::F1);
+        let pressed = |events: &[egui::Event]| {
+            events
+                .iter()
+                .any(|event| matches!(event, egui::Event::PointerButton { pressed: true, .. }))
+        };
+
+        // while hidden, egui gets no input but window state is still tracked:
+        simulate::feed(&mut state, &platform, input.click(egui::pos2(10.0, 10.0)));
+        let resize = sdl2::event::Event::Window {
+            timestamp: 0,
+            window_id: platform.window_id,
+            win_event: WindowEvent::Resized(400, 200),
+        };
+        state.sdl2_input_to_egui(&platform, &resize);
+        state.sdl2_input_to_egui(&platform, &sdl2::event::Event::Quit { timestamp: 0 });
+        let raw_input = state.raw_input.take();
+        assert!(!pressed(&raw_input.events));
+        assert_eq!(
+            raw_input.screen_rect,
+            Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(200.0, 100.0)))
+        );
+        let viewport = &raw_input.viewports[&raw_input.viewport_id];
+        assert!(viewport.events.contains(&egui::ViewportEvent::Close));
+
+        simulate::feed(&mut state, &platform, input.key_press(Keycode::F1));
+        assert!(state.overlay_visible());
+        assert!(!platform.relative_mouse_mode.get());
+        assert!(!platform.mouse_grab.get());
+        assert!(platform.cursor_visible.get());
+        simulate::feed(&mut state, &platform, input.click(egui::pos2(10.0, 10.0)));
+        assert!(pressed(&state.raw_input.take().events));
+
+        simulate::feed(&mut state, &platform, input.key_press(Keycode::F1));
+        assert!(!state.overlay_visible());
+        assert!(platform.relative_mouse_mode.get());
+        assert!(platform.mouse_grab.get());
+        assert!(!platform.cursor_visible.get());
+        assert!(state
+            .raw_input
+            .take()
+            .events
+            .contains(&egui::Event::PointerGone));
+
+        // turning overlay mode off while shown gives the mouse back as well:
+        simulate::feed(&mut state, &platform, input.key_press(Keycode::F1));
+        state.disable_overlay(&platform);
+        assert!(state.overlay_visible());
+        assert!(platform.relative_mouse_mode.get());
+        assert!(platform.mouse_grab.get());
+        assert!(!platform.cursor_visible.get());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::platform::Platform;
+use sdl2::keyboard::Keycode;
+
+/// Mouse settings of the game, saved while the overlay is shown.
+#[derive(Clone, Copy, Debug)]
+struct GameMouseState {
+    relative_mouse_mode: bool,
+    mouse_grab: bool,
+    cursor_visible: bool,
+}
+
+impl GameMouseState {
+    fn save(platform: &(impl Platform + ?Sized)) -> Self {
+        Self {
+            relative_mouse_mode: platform.relative_mouse_mode(),
+            mouse_grab: platform.mouse_grab(),
+            cursor_visible: platform.cursor_visible(),
+        }
+    }
+
+    fn restore(&self, platform: &(impl Platform + ?Sized)) {
+        platform.set_relative_mouse_mode(self.relative_mouse_mode);
+        platform.show_cursor(self.cursor_visible);
+        platform.set_mouse_grab(self.mouse_grab);
+    }
+}
+
+/// Overlay mode of [`crate::EguiSDL2State`], see [`crate::EguiSDL2State::enable_overlay`].
+#[derive(Clone, Debug)]
+pub(crate) struct Overlay {
+    pub(crate) hotkey: Keycode,
+    saved: Option<GameMouseState>,
+}
+
+impl Overlay {
+    pub(crate) fn new(hotkey: Keycode) -> Self {
+        Self {
+            hotkey,
+            saved: None,
+        }
+    }
+
+    pub(crate) fn is_visible(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Hands the mouse to egui: the cursor is released and shown.
+    pub(crate) fn show(&mut self, platform: &(impl Platform + ?Sized)) {
+        if self.saved.is_some() {
+            return;
+        }
+        self.saved = Some(GameMouseState::save(platform));
+        platform.set_relative_mouse_mode(false);
+        platform.show_cursor(true);
+        platform.set_mouse_grab(false);
+    }
+
+    /// Gives the mouse back to the game in the state it was in before [`Overlay::show`].
+    pub(crate) fn hide(&mut self, platform: &(impl Platform + ?Sized)) {
+        if let Some(saved) = self.saved.take() {
+            saved.restore(platform);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::MockPlatform;
+
+    #[test]
+    fn showing_twice_keeps_the_game_state() {
+        let platform = MockPlatform::default();
+        platform.relative_mouse_mode.set(true);
+        platform.cursor_visible.set(false);
+        let mut overlay = Overlay::new(Keycode::F1);
+
+        overlay.show(&platform);
+        overlay.show(&platform);
+        assert!(overlay.is_visible());
+        assert!(!platform.relative_mouse_mode.get());
+        assert!(platform.cursor_visible.get());
+
+        overlay.hide(&platform);
+        assert!(!overlay.is_visible());
+        assert!(platform.relative_mouse_mode.get());
+        assert!(!platform.mouse_grab.get());
+        assert!(!platform.cursor_visible.get());
+    }
+}
@@ -0,0 +1,371 @@
+use super::Painter;
+use crate::{Error, Result};
+use egui::epaint::{ImageDelta, Primitive};
+use egui::{
+    ClippedPrimitive, ColorImage, ImageData, Rect, TextureFilter, TextureId, TexturesDelta,
+};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{Canvas, Texture};
+use sdl2::sys;
+use sdl2::video::Window;
+use std::collections::HashMap;
+use std::ffi::c_int;
+
+/// An SDL texture used by the painter, created for egui or registered by the application.
+struct SdlTexture {
+    raw: *mut sys::SDL_Texture,
+    size: [usize; 2],
+    /// `false` for textures registered with [`CanvasPainter::register_sdl_texture`].
+    owned: bool,
+}
+
+impl SdlTexture {
+    fn new(canvas: &Canvas<Window>, size: [usize; 2]) -> Result<Self> {
+        let raw = unsafe {
+            sys::SDL_CreateTexture(
+                canvas.raw(),
+                sys::SDL_PixelFormatEnum::SDL_PIXELFORMAT_RGBA32 as u32,
+                sys::SDL_TextureAccess::SDL_TEXTUREACCESS_STATIC as c_int,
+                size[0] as c_int,
+                size[1] as c_int,
+            )
+        };
+        if raw.is_null() {
+            return Err(Error::Sdl(sdl2::get_error()));
+        }
+        unsafe { sys::SDL_SetTextureBlendMode(raw, sys::SDL_BlendMode::SDL_BLENDMODE_BLEND) };
+        Ok(Self {
+            raw,
+            size,
+            owned: true,
+        })
+    }
+}
+
+impl Drop for SdlTexture {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe { sys::SDL_DestroyTexture(self.raw) };
+        }
+    }
+}
+
+/// Paints egui through an [`sdl2::render::Canvas`] with `SDL_RenderGeometry`, so it works
+/// with every SDL renderer including the software one. Requires SDL 2.0.18.
+///
+/// Paint callbacks are not supported and texture wrap modes are ignored.
+pub struct CanvasPainter {
+    // declared before `canvas`, textures have to be destroyed before their renderer:
+    textures: HashMap<TextureId, SdlTexture>,
+    canvas: Canvas<Window>,
+    vertices: Vec<sys::SDL_Vertex>,
+    indices: Vec<c_int>,
+    clear_color: Color,
+    next_user_texture_id: u64,
+}
+
+impl CanvasPainter {
+    /// Creates a painter with the default renderer of `window`.
+    pub fn new(window: Window) -> Result<Self> {
+        let canvas = window
+            .into_canvas()
+            .build()
+            .map_err(|e| Error::Sdl(e.to_string()))?;
+        Ok(Self::from_canvas(canvas))
+    }
+
+    /// Uses an existing canvas, e.g. one created with `CanvasBuilder::software`.
+    pub fn from_canvas(canvas: Canvas<Window>) -> Self {
+        Self {
+            textures: HashMap::new(),
+            canvas,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            clear_color: Color::BLACK,
+            next_user_texture_id: 0,
+        }
+    }
+
+    /// The canvas, for drawing the game below egui.
+    pub fn canvas(&self) -> &Canvas<Window> {
+        &self.canvas
+    }
+
+    pub fn canvas_mut(&mut self) -> &mut Canvas<Window> {
+        &mut self.canvas
+    }
+
+    /// Color the canvas is cleared with by [`Painter::paint`].
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    /// Creates, updates and resizes the textures in `textures_delta.set`. Call before
+    /// [`CanvasPainter::render`].
+    pub fn update_textures(&mut self, textures_delta: &TexturesDelta) -> Result<()> {
+        for (id, delta) in &textures_delta.set {
+            self.update_texture(*id, delta)?;
+        }
+        Ok(())
+    }
+
+    /// Frees the textures in `textures_delta.free`. Call after [`CanvasPainter::render`].
+    pub fn free_textures(&mut self, textures_delta: &TexturesDelta) {
+        for id in &textures_delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    /// Makes `texture` available to egui, e.g. for `egui::Image`, without copying it.
+    ///
+    /// Vertex colors modulate the texture and its own blend mode is used.
+    ///
+    /// # Safety
+    ///
+    /// `texture` has to be created from this painter's canvas and must stay alive until it is
+    /// replaced with [`CanvasPainter::update_sdl_texture`] or unregistered with
+    /// [`CanvasPainter::unregister_sdl_texture`].
+    pub unsafe fn register_sdl_texture(&mut self, texture: &Texture) -> TextureId {
+        let id = TextureId::User(self.next_user_texture_id);
+        self.next_user_texture_id += 1;
+        self.insert_sdl_texture(id, texture);
+        id
+    }
+
+    /// Shows `texture` for the user texture `id` from now on. Fails for textures managed by
+    /// egui, which would be overwritten or freed by the next [`TexturesDelta`].
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`CanvasPainter::register_sdl_texture`].
+    pub unsafe fn update_sdl_texture(&mut self, id: TextureId, texture: &Texture) -> Result<()> {
+        if !matches!(id, TextureId::User(_)) {
+            return Err(Error::Painter(format!(
+                "{id:?} is managed by egui and can't be replaced with an SDL texture"
+            )));
+        }
+        self.insert_sdl_texture(id, texture);
+        Ok(())
+    }
+
+    fn insert_sdl_texture(&mut self, id: TextureId, texture: &Texture) {
+        let query = texture.query();
+        self.textures.insert(
+            id,
+            SdlTexture {
+                raw: texture.raw(),
+                size: [query.width as usize, query.height as usize],
+                owned: false,
+            },
+        );
+    }
+
+    /// Forgets a texture registered with [`CanvasPainter::register_sdl_texture`]. The SDL
+    /// texture itself is left alone.
+    pub fn unregister_sdl_texture(&mut self, id: TextureId) {
+        if matches!(self.textures.get(&id), Some(texture) if !texture.owned) {
+            self.textures.remove(&id);
+        }
+    }
+
+    fn update_texture(&mut self, id: TextureId, delta: &ImageDelta) -> Result<()> {
+        let ImageData::Color(image) = &delta.image;
+        let [width, height] = image.size;
+        // SDL blends unmultiplied colors; texture and vertex alpha multiply to egui's result:
+        let pixels: Vec<u8> = image
+            .pixels
+            .iter()
+            .flat_map(|color| color.to_srgba_unmultiplied())
+            .collect();
+
+        let origin = match delta.pos {
+            Some(pos) => pos,
+            None => {
+                let reuse =
+                    matches!(self.textures.get(&id), Some(texture) if texture.size == image.size);
+                if !reuse {
+                    self.textures
+                        .insert(id, SdlTexture::new(&self.canvas, image.size)?);
+                }
+                [0, 0]
+            }
+        };
+        let Some(texture) = self.textures.get(&id) else {
+            warn!("partial update of unknown texture {id:?}");
+            return Ok(());
+        };
+
+        let rect = sys::SDL_Rect {
+            x: origin[0] as c_int,
+            y: origin[1] as c_int,
+            w: width as c_int,
+            h: height as c_int,
+        };
+        let scale_mode = match delta.options.magnification {
+            TextureFilter::Nearest => sys::SDL_ScaleMode::SDL_ScaleModeNearest,
+            TextureFilter::Linear => sys::SDL_ScaleMode::SDL_ScaleModeLinear,
+        };
+        unsafe {
+            if sys::SDL_UpdateTexture(
+                texture.raw,
+                &rect,
+                pixels.as_ptr().cast(),
+                (width * 4) as c_int,
+            ) != 0
+            {
+                return Err(Error::Sdl(sdl2::get_error()));
+            }
+            sys::SDL_SetTextureScaleMode(texture.raw, scale_mode);
+        }
+        Ok(())
+    }
+
+    /// Draws `clipped_primitives` on top of whatever is on the canvas, without presenting.
+    pub fn render(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()> {
+        let (output_width, output_height) = self.canvas.output_size().map_err(Error::Sdl)?;
+        let (window_width, _) = self.canvas.window().size();
+        // points to renderer pixels, which differ from window coordinates on high DPI displays:
+        let scale = pixels_per_point * output_width as f32 / window_width.max(1) as f32;
+        let output = Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(output_width as f32, output_height as f32),
+        );
+
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in clipped_primitives
+        {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let clip = (*clip_rect * scale).intersect(output);
+            let clip = Rect::from_min_max(clip.min.round(), clip.max.round());
+            if !clip.is_positive() {
+                continue;
+            }
+            let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                warn!("mesh uses unknown texture {:?}", mesh.texture_id);
+                continue;
+            };
+
+            self.vertices.clear();
+            self.vertices.extend(mesh.vertices.iter().map(|vertex| {
+                let [r, g, b, a] = vertex.color.to_srgba_unmultiplied();
+                sys::SDL_Vertex {
+                    position: sys::SDL_FPoint {
+                        x: vertex.pos.x * scale,
+                        y: vertex.pos.y * scale,
+                    },
+                    color: sys::SDL_Color { r, g, b, a },
+                    tex_coord: sys::SDL_FPoint {
+                        x: vertex.uv.x,
+                        y: vertex.uv.y,
+                    },
+                }
+            }));
+            self.indices.clear();
+            self.indices
+                .extend(mesh.indices.iter().map(|&index| index as c_int));
+
+            self.canvas.set_clip_rect(sdl2::rect::Rect::new(
+                clip.min.x as i32,
+                clip.min.y as i32,
+                clip.width() as u32,
+                clip.height() as u32,
+            ));
+            let result = unsafe {
+                sys::SDL_RenderGeometry(
+                    self.canvas.raw(),
+                    texture.raw,
+                    self.vertices.as_ptr(),
+                    self.vertices.len() as c_int,
+                    self.indices.as_ptr(),
+                    self.indices.len() as c_int,
+                )
+            };
+            if result != 0 {
+                self.canvas.set_clip_rect(None);
+                return Err(Error::Sdl(sdl2::get_error()));
+            }
+        }
+        self.canvas.set_clip_rect(None);
+        Ok(())
+    }
+}
+
+impl Painter for CanvasPainter {
+    fn window(&self) -> &Window {
+        self.canvas.window()
+    }
+
+    fn window_mut(&mut self) -> &mut Window {
+        self.canvas.window_mut()
+    }
+
+    fn max_texture_side(&self) -> Option<usize> {
+        let info = self.canvas.info();
+        // zero means the renderer has no limit:
+        let side = info.max_texture_width.min(info.max_texture_height);
+        (side > 0).then_some(side as usize)
+    }
+
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()> {
+        self.paint_frame(pixels_per_point, textures_delta, clipped_primitives, false)
+            .map(drop)
+    }
+
+    fn can_capture(&self) -> bool {
+        true
+    }
+
+    fn paint_and_capture(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<Option<ColorImage>> {
+        self.paint_frame(pixels_per_point, textures_delta, clipped_primitives, true)
+    }
+}
+
+impl CanvasPainter {
+    fn paint_frame(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+        capture: bool,
+    ) -> Result<Option<ColorImage>> {
+        self.update_textures(textures_delta)?;
+        self.canvas.set_draw_color(self.clear_color);
+        self.canvas.clear();
+        self.render(pixels_per_point, clipped_primitives)?;
+        // read back before presenting, the back buffer is undefined afterwards:
+        let image = if capture {
+            let (width, height) = self.canvas.output_size().map_err(Error::Sdl)?;
+            let rgba = self
+                .canvas
+                .read_pixels(None, PixelFormatEnum::RGBA32)
+                .map_err(Error::Sdl)?;
+            Some(ColorImage::from_rgba_unmultiplied(
+                [width as usize, height as usize],
+                &rgba,
+            ))
+        } else {
+            None
+        };
+        self.canvas.present();
+        self.free_textures(textures_delta);
+        Ok(image)
+    }
+}
@@ -0,0 +1,170 @@
+use super::Painter;
+use crate::{Error, Result};
+use egui::{ClippedPrimitive, ColorImage, Rgba, TexturesDelta};
+use egui_glow::glow;
+use sdl2::video::{GLContext, SwapInterval, Window};
+use std::sync::Arc;
+
+/// Paints egui with OpenGL through [`egui_glow::Painter`].
+///
+/// The window has to be created with OpenGL support (`WindowBuilder::opengl`). GL context
+/// attributes are taken from `video.gl_attr()` as set before creating the window. Any
+/// context SDL can create works, including Mesa's llvmpipe on machines without a GPU.
+///
+/// Custom GL drawing is done with `egui::PaintCallback`s holding an
+/// [`egui_glow::CallbackFn`].
+pub struct GlowPainter {
+    // dropped in this order: the painter needs the context, the context needs the window
+    painter: egui_glow::Painter,
+    gl_context: GLContext,
+    window: Window,
+    clear_color: Rgba,
+}
+
+impl GlowPainter {
+    /// Creates a GL context for `window` and turns vsync on if the driver supports it.
+    pub fn new(window: Window) -> Result<Self> {
+        let gl_context = window.gl_create_context().map_err(Error::Sdl)?;
+        window.gl_make_current(&gl_context).map_err(Error::Sdl)?;
+        let video = window.subsystem().clone();
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| video.gl_get_proc_address(name).cast())
+        };
+        let painter = egui_glow::Painter::new(Arc::new(gl), "", None, false)
+            .map_err(|e| Error::Painter(e.to_string()))?;
+        let painter = Self {
+            painter,
+            gl_context,
+            window,
+            clear_color: Rgba::BLACK,
+        };
+        if let Err(err) = painter.set_vsync(true) {
+            warn!("{err}");
+        }
+        Ok(painter)
+    }
+
+    /// Waits for the vertical blank when presenting, or not.
+    pub fn set_vsync(&self, vsync: bool) -> Result<()> {
+        let interval = if vsync {
+            SwapInterval::VSync
+        } else {
+            SwapInterval::Immediate
+        };
+        self.window
+            .subsystem()
+            .gl_set_swap_interval(interval)
+            .map_err(Error::Sdl)
+    }
+
+    /// The GL functions, for drawing the game below egui.
+    pub fn gl(&self) -> &Arc<glow::Context> {
+        self.painter.gl()
+    }
+
+    /// The underlying painter, e.g. to register GL textures with
+    /// `egui_glow::Painter::register_native_texture`.
+    pub fn painter(&mut self) -> &mut egui_glow::Painter {
+        &mut self.painter
+    }
+
+    pub fn gl_context(&self) -> &GLContext {
+        &self.gl_context
+    }
+
+    /// Color the framebuffer is cleared with by [`Painter::paint`].
+    pub fn set_clear_color(&mut self, color: Rgba) {
+        self.clear_color = color;
+    }
+
+    /// Draws `clipped_primitives` over the framebuffer and applies `textures_delta`, without
+    /// clearing or swapping.
+    pub fn render(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()> {
+        self.window
+            .gl_make_current(&self.gl_context)
+            .map_err(Error::Sdl)?;
+        let (width, height) = self.window.drawable_size();
+        let (window_width, _) = self.window.size();
+        // the drawable is larger than the window on high DPI displays:
+        let pixels_per_point = pixels_per_point * width as f32 / window_width.max(1) as f32;
+        self.painter.paint_and_update_textures(
+            [width, height],
+            pixels_per_point,
+            clipped_primitives,
+            textures_delta,
+        );
+        Ok(())
+    }
+}
+
+impl Painter for GlowPainter {
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+
+    fn max_texture_side(&self) -> Option<usize> {
+        Some(self.painter.max_texture_side())
+    }
+
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()> {
+        self.paint_frame(pixels_per_point, textures_delta, clipped_primitives, false)
+            .map(drop)
+    }
+
+    fn can_capture(&self) -> bool {
+        true
+    }
+
+    fn paint_and_capture(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<Option<ColorImage>> {
+        self.paint_frame(pixels_per_point, textures_delta, clipped_primitives, true)
+    }
+}
+
+impl GlowPainter {
+    fn paint_frame(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+        capture: bool,
+    ) -> Result<Option<ColorImage>> {
+        self.window
+            .gl_make_current(&self.gl_context)
+            .map_err(Error::Sdl)?;
+        let (width, height) = self.window.drawable_size();
+        self.painter
+            .clear([width, height], self.clear_color.to_array());
+        self.render(pixels_per_point, textures_delta, clipped_primitives)?;
+        // read back before swapping, the back buffer is undefined afterwards:
+        let image = capture.then(|| self.painter.read_screen_rgba([width, height]));
+        self.window.gl_swap_window();
+        Ok(image)
+    }
+}
+
+impl Drop for GlowPainter {
+    fn drop(&mut self) {
+        if self.window.gl_make_current(&self.gl_context).is_ok() {
+            self.painter.destroy();
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Renderers for the output of egui. Each painter is behind its own cargo feature.
+
+use crate::Result;
+use egui::{ClippedPrimitive, ColorImage, TexturesDelta};
+use sdl2::video::Window;
+
+#[cfg(feature = "canvas")]
+mod canvas;
+#[cfg(feature = "glow")]
+mod glow;
+#[cfg(feature = "software")]
+mod software;
+#[cfg(feature = "wgpu")]
+mod wgpu;
+
+#[cfg(feature = "canvas")]
+pub use canvas::CanvasPainter;
+#[cfg(feature = "glow")]
+pub use egui_glow;
+#[cfg(feature = "wgpu")]
+pub use egui_wgpu;
+#[cfg(feature = "glow")]
+pub use glow::GlowPainter;
+#[cfg(feature = "software")]
+pub use software::{PixelRect, SoftwarePainter, SoftwareRenderer};
+#[cfg(feature = "wgpu")]
+pub use wgpu::{WgpuPainter, WgpuPainterOptions};
+
+/// Draws the output of egui into the window it owns.
+///
+/// The painter owns the window because some renderers, e.g. [`sdl2::render::Canvas`], take
+/// it over.
+pub trait Painter {
+    fn window(&self) -> &Window;
+
+    fn window_mut(&mut self) -> &mut Window;
+
+    /// Largest texture the painter can handle, for `RawInput::max_texture_side`.
+    fn max_texture_side(&self) -> Option<usize> {
+        None
+    }
+
+    /// Applies `textures_delta` and presents one frame made of `clipped_primitives`.
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()>;
+
+    /// Whether [`Painter::paint_and_capture`] can read frames back.
+    fn can_capture(&self) -> bool {
+        false
+    }
+
+    /// Like [`Painter::paint`], and reads the presented frame back for
+    /// `ViewportCommand::Screenshot`. `None` if the painter can't read back or skipped the
+    /// frame, e.g. while the window is minimized.
+    fn paint_and_capture(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<Option<ColorImage>> {
+        self.paint(pixels_per_point, textures_delta, clipped_primitives)?;
+        Ok(None)
+    }
+}
@@ -0,0 +1,649 @@
+use super::Painter;
+use crate::{Error, Result};
+use egui::epaint::{ImageDelta, Mesh, Primitive, Vertex};
+use egui::{
+    ClippedPrimitive, Color32, ColorImage, ImageData, Pos2, TextureFilter, TextureId, TexturesDelta,
+};
+use sdl2::sys;
+use sdl2::video::Window;
+use std::collections::HashMap;
+use std::ffi::c_int;
+
+/// Side length of the tiles compared between frames to find what changed.
+const TILE_SIZE: usize = 64;
+
+/// A rectangle of pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A texture in premultiplied RGBA.
+struct SoftwareTexture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+    filter: TextureFilter,
+}
+
+impl SoftwareTexture {
+    /// Samples at texture coordinates `uv`, clamping to the edge.
+    fn sample(&self, uv: Pos2) -> [f32; 4] {
+        let [width, height] = self.size;
+        if width == 0 || height == 0 {
+            return [0.0; 4];
+        }
+        let texel = |x: isize, y: isize| {
+            let x = x.clamp(0, width as isize - 1) as usize;
+            let y = y.clamp(0, height as isize - 1) as usize;
+            self.pixels[y * width + x].to_array().map(f32::from)
+        };
+        let x = uv.x * width as f32 - 0.5;
+        let y = uv.y * height as f32 - 0.5;
+        match self.filter {
+            TextureFilter::Nearest => texel(x.round() as isize, y.round() as isize),
+            TextureFilter::Linear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let top = lerp(texel(x0, y0), texel(x0 + 1, y0), fx);
+                let bottom = lerp(texel(x0, y0 + 1), texel(x0 + 1, y0 + 1), fx);
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`; positive if `p` is left of `a -> b`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (p.x - a.x) * (b.y - a.y) - (p.y - a.y) * (b.x - a.x)
+}
+
+/// Whether pixel centers exactly on the edge `a -> b` belong to the triangle. Exactly one of
+/// two triangles sharing an edge gets them, so nothing is blended twice.
+fn owns_edge(a: Pos2, b: Pos2) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    dy > 0.0 || (dy == 0.0 && dx < 0.0)
+}
+
+/// Rasterizes egui meshes on the CPU into a premultiplied RGBA buffer, without any SDL or GPU
+/// involvement.
+///
+/// Supports textured, vertex-colored triangles with clip rectangles, like egui's GPU
+/// painters. Paint callbacks are skipped.
+pub struct SoftwareRenderer {
+    textures: HashMap<TextureId, SoftwareTexture>,
+    /// The last rendered frame.
+    frame: Vec<Color32>,
+    /// The frame being rendered, swapped with `frame` when done.
+    scratch: Vec<Color32>,
+    size: [usize; 2],
+    clear_color: Color32,
+}
+
+impl Default for SoftwareRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoftwareRenderer {
+    pub fn new() -> Self {
+        Self {
+            textures: HashMap::new(),
+            frame: Vec::new(),
+            scratch: Vec::new(),
+            size: [0, 0],
+            clear_color: Color32::BLACK,
+        }
+    }
+
+    /// Color every frame starts with.
+    pub fn set_clear_color(&mut self, color: Color32) {
+        self.clear_color = color;
+    }
+
+    /// Size of the last rendered frame in pixels.
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    /// The last rendered frame, row by row, in premultiplied RGBA.
+    pub fn pixels(&self) -> &[Color32] {
+        &self.frame
+    }
+
+    pub fn update_textures(&mut self, textures_delta: &TexturesDelta) {
+        for (id, delta) in &textures_delta.set {
+            self.update_texture(*id, delta);
+        }
+    }
+
+    pub fn free_textures(&mut self, textures_delta: &TexturesDelta) {
+        for id in &textures_delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn update_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        let ImageData::Color(image) = &delta.image;
+        match delta.pos {
+            None => {
+                self.textures.insert(
+                    id,
+                    SoftwareTexture {
+                        size: image.size,
+                        pixels: image.pixels.clone(),
+                        filter: delta.options.magnification,
+                    },
+                );
+            }
+            Some([x, y]) => {
+                let Some(texture) = self.textures.get_mut(&id) else {
+                    warn!("partial update of unknown texture {id:?}");
+                    return;
+                };
+                let [width, height] = image.size;
+                if x + width > texture.size[0] || y + height > texture.size[1] {
+                    warn!("partial update outside of texture {id:?}");
+                    return;
+                }
+                for row in 0..height {
+                    let start = (y + row) * texture.size[0] + x;
+                    texture.pixels[start..start + width]
+                        .copy_from_slice(&image.pixels[row * width..(row + 1) * width]);
+                }
+                texture.filter = delta.options.magnification;
+            }
+        }
+    }
+
+    /// Renders a frame of `size` pixels. Returns the tiles that differ from the previous
+    /// frame, or the whole frame if the size changed.
+    pub fn render(
+        &mut self,
+        size: [usize; 2],
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Vec<PixelRect> {
+        let resized = size != self.size;
+        self.size = size;
+        self.scratch.clear();
+        self.scratch.resize(size[0] * size[1], self.clear_color);
+
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in clipped_primitives
+        {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let clip = *clip_rect * pixels_per_point;
+            let clip = [
+                clip.min.x.round().max(0.0) as usize,
+                clip.min.y.round().max(0.0) as usize,
+                (clip.max.x.round().max(0.0) as usize).min(size[0]),
+                (clip.max.y.round().max(0.0) as usize).min(size[1]),
+            ];
+            if clip[0] < clip[2] && clip[1] < clip[3] {
+                self.draw_mesh(mesh, pixels_per_point, clip);
+            }
+        }
+
+        std::mem::swap(&mut self.frame, &mut self.scratch);
+        if resized {
+            return vec![PixelRect {
+                x: 0,
+                y: 0,
+                width: size[0],
+                height: size[1],
+            }];
+        }
+        self.changed_tiles()
+    }
+
+    /// Compares the new `frame` against the previous one, now in `scratch`.
+    fn changed_tiles(&self) -> Vec<PixelRect> {
+        let [width, height] = self.size;
+        let mut dirty = Vec::new();
+        for y in (0..height).step_by(TILE_SIZE) {
+            let tile_height = TILE_SIZE.min(height - y);
+            for x in (0..width).step_by(TILE_SIZE) {
+                let tile_width = TILE_SIZE.min(width - x);
+                let changed = (y..y + tile_height).any(|row| {
+                    let start = row * width + x;
+                    let range = start..start + tile_width;
+                    self.frame[range.clone()] != self.scratch[range]
+                });
+                if changed {
+                    dirty.push(PixelRect {
+                        x,
+                        y,
+                        width: tile_width,
+                        height: tile_height,
+                    });
+                }
+            }
+        }
+        dirty
+    }
+
+    /// Draws the triangles of `mesh` into `scratch`, limited to `clip` given as
+    /// `[min_x, min_y, max_x, max_y]` in pixels.
+    fn draw_mesh(&mut self, mesh: &Mesh, scale: f32, clip: [usize; 4]) {
+        let Some(texture) = self.textures.get(&mesh.texture_id) else {
+            warn!("mesh uses unknown texture {:?}", mesh.texture_id);
+            return;
+        };
+        let width = self.size[0];
+        for triangle in mesh.indices.chunks_exact(3) {
+            let vertex = |i: usize| -> Option<&Vertex> { mesh.vertices.get(triangle[i] as usize) };
+            let (Some(v0), Some(mut v1), Some(mut v2)) = (vertex(0), vertex(1), vertex(2)) else {
+                continue;
+            };
+            let mut area = edge(v0.pos, v1.pos, v2.pos);
+            if area == 0.0 {
+                continue;
+            }
+            // counterclockwise, so the inside is where all edge functions are positive:
+            if area < 0.0 {
+                std::mem::swap(&mut v1, &mut v2);
+                area = -area;
+            }
+            let p = [v0.pos, v1.pos, v2.pos].map(|pos| (pos.to_vec2() * scale).to_pos2());
+            let area = area * scale * scale;
+            let colors = [v0, v1, v2].map(|v| v.color.to_array().map(f32::from));
+            let uvs = [v0.uv, v1.uv, v2.uv];
+            // solid shapes use a single texel, e.g. the white pixel of the font atlas:
+            let constant_texel =
+                (uvs[0] == uvs[1] && uvs[1] == uvs[2]).then(|| texture.sample(uvs[0]));
+
+            let min_x = p
+                .iter()
+                .map(|p| p.x)
+                .fold(f32::INFINITY, f32::min)
+                .floor()
+                .max(clip[0] as f32) as usize;
+            let min_y = p
+                .iter()
+                .map(|p| p.y)
+                .fold(f32::INFINITY, f32::min)
+                .floor()
+                .max(clip[1] as f32) as usize;
+            let max_x = (p
+                .iter()
+                .map(|p| p.x)
+                .fold(f32::NEG_INFINITY, f32::max)
+                .ceil()
+                .max(0.0) as usize)
+                .min(clip[2]);
+            let max_y = (p
+                .iter()
+                .map(|p| p.y)
+                .fold(f32::NEG_INFINITY, f32::max)
+                .ceil()
+                .max(0.0) as usize)
+                .min(clip[3]);
+            let owns = [
+                owns_edge(p[1], p[2]),
+                owns_edge(p[2], p[0]),
+                owns_edge(p[0], p[1]),
+            ];
+
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    let center = egui::pos2(x as f32 + 0.5, y as f32 + 0.5);
+                    let weights = [
+                        edge(p[1], p[2], center),
+                        edge(p[2], p[0], center),
+                        edge(p[0], p[1], center),
+                    ];
+                    let inside = weights
+                        .iter()
+                        .zip(owns)
+                        .all(|(&w, owns)| w > 0.0 || (w == 0.0 && owns));
+                    if !inside {
+                        continue;
+                    }
+                    let weights = weights.map(|w| w / area);
+                    let color: [f32; 4] = std::array::from_fn(|i| {
+                        colors[0][i] * weights[0]
+                            + colors[1][i] * weights[1]
+                            + colors[2][i] * weights[2]
+                    });
+                    let texel = constant_texel.unwrap_or_else(|| {
+                        let uv = egui::pos2(
+                            uvs[0].x * weights[0] + uvs[1].x * weights[1] + uvs[2].x * weights[2],
+                            uvs[0].y * weights[0] + uvs[1].y * weights[1] + uvs[2].y * weights[2],
+                        );
+                        texture.sample(uv)
+                    });
+                    let src: [f32; 4] = std::array::from_fn(|i| color[i] * texel[i] / 255.0);
+                    let dst = &mut self.scratch[y * width + x];
+                    let keep = 1.0 - src[3] / 255.0;
+                    let blended: [u8; 4] = std::array::from_fn(|i| {
+                        (src[i] + f32::from(dst.to_array()[i]) * keep)
+                            .round()
+                            .clamp(0.0, 255.0) as u8
+                    });
+                    *dst = Color32::from_rgba_premultiplied(
+                        blended[0], blended[1], blended[2], blended[3],
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Paints egui without any GPU: [`SoftwareRenderer`] rasterizes on the CPU and only the
+/// changed tiles are copied to the window surface (`SDL_GetWindowSurface`).
+///
+/// The window must not be used with an SDL renderer or OpenGL at the same time.
+pub struct SoftwarePainter {
+    renderer: SoftwareRenderer,
+    window: Window,
+    /// The window surface written last frame. A new surface has to be filled completely.
+    surface: *mut sys::SDL_Surface,
+}
+
+impl SoftwarePainter {
+    pub fn new(window: Window) -> Self {
+        Self {
+            renderer: SoftwareRenderer::new(),
+            window,
+            surface: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn renderer(&mut self) -> &mut SoftwareRenderer {
+        &mut self.renderer
+    }
+
+    /// Copies the `dirty` parts of the rendered frame to the window surface and shows them.
+    fn present(&mut self, mut dirty: Vec<PixelRect>, surface: *mut sys::SDL_Surface) -> Result<()> {
+        if surface != self.surface {
+            let [width, height] = self.renderer.size();
+            dirty = vec![PixelRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }];
+            self.surface = surface;
+        }
+        if dirty.is_empty() {
+            return Ok(());
+        }
+        let src_pitch = self.renderer.size()[0] * 4;
+        let src = self.renderer.pixels().as_ptr().cast::<u8>();
+        unsafe {
+            if sys::SDL_LockSurface(surface) != 0 {
+                return Err(Error::Sdl(sdl2::get_error()));
+            }
+            let surface_ref = &*surface;
+            let format = (*surface_ref.format).format;
+            let bytes_per_pixel = (*surface_ref.format).BytesPerPixel as usize;
+            let dst_pitch = surface_ref.pitch as usize;
+            let dst = surface_ref.pixels.cast::<u8>();
+            for rect in &dirty {
+                let result = sys::SDL_ConvertPixels(
+                    rect.width as c_int,
+                    rect.height as c_int,
+                    sys::SDL_PixelFormatEnum::SDL_PIXELFORMAT_RGBA32 as u32,
+                    src.add(rect.y * src_pitch + rect.x * 4).cast(),
+                    src_pitch as c_int,
+                    format,
+                    dst.add(rect.y * dst_pitch + rect.x * bytes_per_pixel)
+                        .cast(),
+                    dst_pitch as c_int,
+                );
+                if result != 0 {
+                    sys::SDL_UnlockSurface(surface);
+                    return Err(Error::Sdl(sdl2::get_error()));
+                }
+            }
+            sys::SDL_UnlockSurface(surface);
+        }
+
+        let rects: Vec<sys::SDL_Rect> = dirty
+            .iter()
+            .map(|rect| sys::SDL_Rect {
+                x: rect.x as c_int,
+                y: rect.y as c_int,
+                w: rect.width as c_int,
+                h: rect.height as c_int,
+            })
+            .collect();
+        let result = unsafe {
+            sys::SDL_UpdateWindowSurfaceRects(
+                self.window.raw(),
+                rects.as_ptr(),
+                rects.len() as c_int,
+            )
+        };
+        if result != 0 {
+            return Err(Error::Sdl(sdl2::get_error()));
+        }
+        Ok(())
+    }
+}
+
+impl Painter for SoftwarePainter {
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()> {
+        self.renderer.update_textures(textures_delta);
+        // the surface is recreated by SDL when the window was resized:
+        let surface = unsafe { sys::SDL_GetWindowSurface(self.window.raw()) };
+        let result = if surface.is_null() {
+            Err(Error::Sdl(sdl2::get_error()))
+        } else {
+            let (width, height) = unsafe { ((*surface).w as usize, (*surface).h as usize) };
+            let (window_width, _) = self.window.size();
+            // the surface is larger than the window on high DPI displays:
+            let scale = pixels_per_point * width as f32 / window_width.max(1) as f32;
+            let dirty = self
+                .renderer
+                .render([width, height], scale, clipped_primitives);
+            self.present(dirty, surface)
+        };
+        self.renderer.free_textures(textures_delta);
+        result
+    }
+
+    fn can_capture(&self) -> bool {
+        true
+    }
+
+    fn paint_and_capture(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<Option<ColorImage>> {
+        self.paint(pixels_per_point, textures_delta, clipped_primitives)?;
+        let pixels = self.renderer.pixels().to_vec();
+        Ok(Some(ColorImage::new(self.renderer.size(), pixels)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, Rect, TextureOptions};
+
+    const WHITE_TEXTURE: TextureId = TextureId::Managed(0);
+
+    fn renderer() -> SoftwareRenderer {
+        let mut renderer = SoftwareRenderer::new();
+        renderer.update_textures(&TexturesDelta {
+            set: vec![(
+                WHITE_TEXTURE,
+                ImageDelta::full(
+                    ColorImage::new([1, 1], vec![Color32::WHITE]),
+                    TextureOptions::NEAREST,
+                ),
+            )],
+            free: Vec::new(),
+        });
+        renderer
+    }
+
+    fn primitive(clip_rect: Rect, mesh: Mesh) -> ClippedPrimitive {
+        ClippedPrimitive {
+            clip_rect,
+            primitive: Primitive::Mesh(mesh),
+        }
+    }
+
+    fn rects(rects: &[Rect], color: Color32) -> Mesh {
+        let mut mesh = Mesh::with_texture(WHITE_TEXTURE);
+        for rect in rects {
+            mesh.add_colored_rect(*rect, color);
+        }
+        mesh
+    }
+
+    fn rect(min: (f32, f32), max: (f32, f32)) -> Rect {
+        Rect::from_min_max(pos2(min.0, min.1), pos2(max.0, max.1))
+    }
+
+    #[test]
+    fn shared_edges_are_filled_once() {
+        let mut renderer = renderer();
+        let translucent = Color32::from_rgba_premultiplied(100, 0, 0, 128);
+        // the diagonals of both quads and the edge between them run through pixel centers:
+        let mesh = rects(
+            &[rect((0.0, 0.0), (2.5, 4.0)), rect((2.5, 0.0), (4.0, 4.0))],
+            translucent,
+        );
+        renderer.render([4, 4], 1.0, &[primitive(Rect::EVERYTHING, mesh)]);
+        let once = Color32::from_rgba_premultiplied(100, 0, 0, 255);
+        assert!(renderer.pixels().iter().all(|&pixel| pixel == once));
+    }
+
+    #[test]
+    fn meshes_are_clipped() {
+        let mut renderer = renderer();
+        let mesh = rects(&[rect((0.0, 0.0), (8.0, 8.0))], Color32::RED);
+        let clip = rect((1.0, 1.0), (3.0, 3.0));
+        // clip rects are in points:
+        renderer.render([8, 8], 2.0, &[primitive(clip, mesh)]);
+        for (i, &pixel) in renderer.pixels().iter().enumerate() {
+            let (x, y) = (i % 8, i / 8);
+            let inside = (2..6).contains(&x) && (2..6).contains(&y);
+            let expected = if inside { Color32::RED } else { Color32::BLACK };
+            assert_eq!(pixel, expected, "pixel {x}, {y}");
+        }
+    }
+
+    #[test]
+    fn partial_texture_updates() {
+        let mut renderer = SoftwareRenderer::new();
+        let texture = TextureId::User(1);
+        let red = ColorImage::new([2, 2], vec![Color32::RED; 4]);
+        let blue = ColorImage::new([1, 1], vec![Color32::BLUE]);
+        renderer.update_textures(&TexturesDelta {
+            set: vec![
+                (texture, ImageDelta::full(red, TextureOptions::NEAREST)),
+                (
+                    texture,
+                    ImageDelta::partial([1, 0], blue, TextureOptions::NEAREST),
+                ),
+            ],
+            free: Vec::new(),
+        });
+        let mut mesh = Mesh::with_texture(texture);
+        mesh.add_rect_with_uv(
+            rect((0.0, 0.0), (2.0, 2.0)),
+            rect((0.0, 0.0), (1.0, 1.0)),
+            Color32::WHITE,
+        );
+        renderer.render([2, 2], 1.0, &[primitive(Rect::EVERYTHING, mesh)]);
+        assert_eq!(
+            renderer.pixels(),
+            [Color32::RED, Color32::BLUE, Color32::RED, Color32::RED]
+        );
+    }
+
+    #[test]
+    fn blending_is_premultiplied() {
+        let mut renderer = renderer();
+        let full = rect((0.0, 0.0), (1.0, 1.0));
+        let primitives = [
+            primitive(Rect::EVERYTHING, rects(&[full], Color32::WHITE)),
+            primitive(
+                Rect::EVERYTHING,
+                rects(&[full], Color32::from_rgba_premultiplied(0, 0, 128, 128)),
+            ),
+        ];
+        renderer.render([1, 1], 1.0, &primitives);
+        assert_eq!(
+            renderer.pixels(),
+            [Color32::from_rgba_premultiplied(127, 127, 255, 255)]
+        );
+    }
+
+    #[test]
+    fn only_changed_tiles_are_reported() {
+        let mut renderer = renderer();
+        let size = [130, 70];
+        let dot = |x: f32, y: f32| {
+            let mesh = rects(&[rect((x, y), (x + 1.0, y + 1.0))], Color32::WHITE);
+            [primitive(Rect::EVERYTHING, mesh)]
+        };
+        let everything = PixelRect {
+            x: 0,
+            y: 0,
+            width: 130,
+            height: 70,
+        };
+        assert_eq!(renderer.render(size, 1.0, &[]), [everything]);
+        assert_eq!(renderer.render(size, 1.0, &[]), []);
+        assert_eq!(
+            renderer.render(size, 1.0, &dot(100.0, 66.0)),
+            [PixelRect {
+                x: 64,
+                y: 64,
+                width: 64,
+                height: 6,
+            }]
+        );
+        // the dot disappears from there and shows up in the last, narrow column:
+        assert_eq!(
+            renderer.render(size, 1.0, &dot(129.0, 0.0)),
+            [
+                PixelRect {
+                    x: 128,
+                    y: 0,
+                    width: 2,
+                    height: 64,
+                },
+                PixelRect {
+                    x: 64,
+                    y: 64,
+                    width: 64,
+                    height: 6,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use super::Painter;
+use crate::{Error, Result};
+use egui::{ClippedPrimitive, Color32, ColorImage, TexturesDelta};
+use egui_wgpu::capture::CaptureState;
+use egui_wgpu::wgpu;
+use egui_wgpu::{Renderer, RendererOptions, ScreenDescriptor};
+use sdl2::video::Window;
+
+/// Settings for [`WgpuPainter::new`].
+#[derive(Clone, Debug)]
+pub struct WgpuPainterOptions {
+    /// Samples per pixel, `1` turns multisampling off.
+    pub msaa_samples: u32,
+    /// Format of a depth buffer for [`egui_wgpu::Callback`]s that need one.
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub present_mode: wgpu::PresentMode,
+    pub power_preference: wgpu::PowerPreference,
+    pub clear_color: wgpu::Color,
+    pub dithering: bool,
+}
+
+impl Default for WgpuPainterOptions {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 1,
+            depth_format: None,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            power_preference: wgpu::PowerPreference::None,
+            clear_color: wgpu::Color::BLACK,
+            dithering: false,
+        }
+    }
+}
+
+/// A render attachment that follows the size of the surface.
+struct Attachment {
+    view: wgpu::TextureView,
+    size: [u32; 2],
+}
+
+impl Attachment {
+    fn new(
+        device: &wgpu::Device,
+        label: &str,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        size: [u32; 2],
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            size,
+        }
+    }
+}
+
+/// Paints egui with [`egui_wgpu`] into a surface created from the SDL window.
+///
+/// The surface is reconfigured when the window size changes and when it was lost or became
+/// outdated. [`egui_wgpu::Callback`]s get their resources from
+/// `renderer().callback_resources`.
+pub struct WgpuPainter {
+    renderer: Renderer,
+    msaa: Option<Attachment>,
+    depth: Option<Attachment>,
+    capture: Option<CaptureState>,
+    // declared before `window`, the surface must not outlive it:
+    surface: wgpu::Surface<'static>,
+    surface_config: wgpu::SurfaceConfiguration,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    options: WgpuPainterOptions,
+    window: Window,
+}
+
+impl WgpuPainter {
+    /// Creates a surface for `window` and picks an adapter and device that can present to it.
+    pub fn new(window: Window, options: WgpuPainterOptions) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        // `window` is stored next to the surface and dropped after it:
+        let surface = unsafe {
+            let target = wgpu::SurfaceTargetUnsafe::from_window(&window)
+                .map_err(|e| Error::Painter(e.to_string()))?;
+            instance.create_surface_unsafe(target)
+        }
+        .map_err(|e| Error::Painter(e.to_string()))?;
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: options.power_preference,
+            force_fallback_adapter: false,
+            compatible_surface: Some(&surface),
+        }))
+        .map_err(|e| Error::Painter(e.to_string()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("egui-sdl2"),
+            ..Default::default()
+        }))
+        .map_err(|e| Error::Painter(e.to_string()))?;
+
+        let (width, height) = window.drawable_size();
+        let mut surface_config = surface
+            .get_default_config(&adapter, width.max(1), height.max(1))
+            .ok_or_else(|| Error::Painter("surface is not supported by the adapter".into()))?;
+        surface_config.present_mode = options.present_mode;
+        surface.configure(&device, &surface_config);
+
+        let renderer = Renderer::new(
+            &device,
+            surface_config.format,
+            RendererOptions {
+                msaa_samples: options.msaa_samples,
+                depth_stencil_format: options.depth_format,
+                dithering: options.dithering,
+                predictable_texture_filtering: false,
+            },
+        );
+        Ok(Self {
+            renderer,
+            msaa: None,
+            depth: None,
+            capture: None,
+            surface,
+            surface_config,
+            device,
+            queue,
+            options,
+            window,
+        })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// The egui renderer, e.g. for `callback_resources` or `register_native_texture`.
+    pub fn renderer(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+
+    pub fn surface_config(&self) -> &wgpu::SurfaceConfiguration {
+        &self.surface_config
+    }
+
+    fn configure_surface(&mut self, width: u32, height: u32) {
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+        self.surface.configure(&self.device, &self.surface_config);
+    }
+
+    /// Returns the color target, the resolve target and the depth target of this frame.
+    fn attachments<'a>(
+        &'a mut self,
+        frame_view: &'a wgpu::TextureView,
+    ) -> (
+        &'a wgpu::TextureView,
+        Option<&'a wgpu::TextureView>,
+        Option<&'a wgpu::TextureView>,
+    ) {
+        let size = [self.surface_config.width, self.surface_config.height];
+        let samples = self.options.msaa_samples.max(1);
+        if samples > 1 && self.msaa.as_ref().map(|msaa| msaa.size) != Some(size) {
+            self.msaa = Some(Attachment::new(
+                &self.device,
+                "egui_msaa",
+                self.surface_config.format,
+                samples,
+                size,
+            ));
+        }
+        if let Some(format) = self.options.depth_format {
+            if self.depth.as_ref().map(|depth| depth.size) != Some(size) {
+                self.depth = Some(Attachment::new(
+                    &self.device,
+                    "egui_depth",
+                    format,
+                    samples,
+                    size,
+                ));
+            }
+        }
+        let depth = self.depth.as_ref().map(|depth| &depth.view);
+        match &self.msaa {
+            Some(msaa) if samples > 1 => (&msaa.view, Some(frame_view), depth),
+            _ => (frame_view, None, depth),
+        }
+    }
+}
+
+impl Painter for WgpuPainter {
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+
+    fn max_texture_side(&self) -> Option<usize> {
+        Some(self.device.limits().max_texture_dimension_2d as usize)
+    }
+
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<()> {
+        self.paint_frame(pixels_per_point, textures_delta, clipped_primitives, false)
+            .map(drop)
+    }
+
+    fn can_capture(&self) -> bool {
+        true
+    }
+
+    fn paint_and_capture(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) -> Result<Option<ColorImage>> {
+        self.paint_frame(pixels_per_point, textures_delta, clipped_primitives, true)
+    }
+}
+
+impl WgpuPainter {
+    fn paint_frame(
+        &mut self,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+        capture: bool,
+    ) -> Result<Option<ColorImage>> {
+        for (id, image_delta) in &textures_delta.set {
+            self.renderer
+                .update_texture(&self.device, &self.queue, *id, image_delta);
+        }
+        let result = self.render_frame(pixels_per_point, clipped_primitives, capture);
+        for id in &textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+        result
+    }
+
+    /// Renders and presents one frame. Skips it if the surface is unavailable right now.
+    ///
+    /// With `capture`, egui is drawn into a texture that is then copied both to the surface
+    /// and to the CPU, since the surface itself usually can't be read.
+    fn render_frame(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+        capture: bool,
+    ) -> Result<Option<ColorImage>> {
+        let (width, height) = self.window.drawable_size();
+        if width == 0 || height == 0 {
+            // minimized, nothing to present:
+            return Ok(None);
+        }
+        if [width, height] != [self.surface_config.width, self.surface_config.height] {
+            self.configure_surface(width, height);
+        }
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                self.configure_surface(width, height);
+                return Ok(None);
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                warn!("timed out waiting for the next surface texture");
+                return Ok(None);
+            }
+            Err(err) => return Err(Error::Painter(err.to_string())),
+        };
+
+        let (window_width, _) = self.window.size();
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [width, height],
+            // the drawable is larger than the window on high DPI displays:
+            pixels_per_point: pixels_per_point * width as f32 / window_width.max(1) as f32,
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("egui_encoder"),
+            });
+        let callback_buffers = self.renderer.update_buffers(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            clipped_primitives,
+            &screen_descriptor,
+        );
+
+        let target = if capture {
+            let capture = self
+                .capture
+                .get_or_insert_with(|| CaptureState::new(&self.device, &frame.texture));
+            capture.update(&self.device, &frame.texture);
+            &capture.texture
+        } else {
+            &frame.texture
+        };
+        let frame_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let clear_color = self.options.clear_color;
+        let (view, resolve_target, depth) = self.attachments(&frame_view);
+        let mut render_pass = encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui_render"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    depth_slice: None,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: depth.map(|view| {
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Discard,
+                        }),
+                        stencil_ops: None,
+                    }
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            })
+            .forget_lifetime();
+        self.renderer
+            .render(&mut render_pass, clipped_primitives, &screen_descriptor);
+        drop(render_pass);
+
+        let capture_buffer = match &mut self.capture {
+            Some(state) if capture => Some(state.copy_textures(&self.device, &frame, &mut encoder)),
+            _ => None,
+        };
+        self.queue.submit(
+            callback_buffers
+                .into_iter()
+                .chain(std::iter::once(encoder.finish())),
+        );
+        frame.present();
+        capture_buffer
+            .map(|buffer| self.read_capture(&buffer, [width, height]))
+            .transpose()
+    }
+
+    /// Waits for the copy of a captured frame and converts it to a [`ColorImage`].
+    fn read_capture(&self, buffer: &wgpu::Buffer, [width, height]: [u32; 2]) -> Result<ColorImage> {
+        let format = self.surface_config.format;
+        let to_rgba = match format.remove_srgb_suffix() {
+            wgpu::TextureFormat::Rgba8Unorm => [0, 1, 2, 3],
+            wgpu::TextureFormat::Bgra8Unorm => [2, 1, 0, 3],
+            _ => {
+                return Err(Error::Painter(format!(
+                    "can't capture a surface with format {format:?}"
+                )))
+            }
+        };
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| Error::Painter(e.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|e| Error::Painter(e.to_string()))?
+            .map_err(|e| Error::Painter(e.to_string()))?;
+
+        // rows are padded to the alignment of buffer copies:
+        let row_bytes = width * 4;
+        let padded_row_bytes = wgpu::util::align_to(row_bytes, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(padded_row_bytes as usize)
+            .take(height as usize)
+            .flat_map(|row| row[..row_bytes as usize].chunks_exact(4))
+            .map(|color| {
+                Color32::from_rgba_premultiplied(
+                    color[to_rgba[0]],
+                    color[to_rgba[1]],
+                    color[to_rgba[2]],
+                    color[to_rgba[3]],
+                )
+            })
+            .collect();
+        buffer.unmap();
+        Ok(ColorImage::new([width as usize, height as usize], pixels))
+    }
+}
@@ -0,0 +1,332 @@
+use crate::translate::translate_modifiers;
+use crate::{Error, Result};
+use egui::{Modifiers, ViewportCommand};
+use sdl2::mouse::{Cursor, SystemCursor};
+use sdl2::sys::SDL_WindowFlags;
+use sdl2::video::{Window, WindowPos};
+use std::cell::{Cell, RefCell};
+
+/// The windowing services [`crate::EguiSDL2State`] needs while translating events and
+/// processing egui's output.
+///
+/// Implemented for [`sdl2::video::Window`]. [`MockPlatform`] implements it without SDL, so the
+/// state machine can be driven in tests and headless environments.
+pub trait Platform {
+    fn window_id(&self) -> u32;
+
+    /// Size of the window in SDL window coordinates.
+    fn window_size(&self) -> (u32, u32);
+
+    fn clipboard_text(&self) -> Option<String>;
+
+    fn set_clipboard_text(&self, text: &str) -> Result<()>;
+
+    /// Modifier keys currently held down.
+    fn modifier_state(&self) -> Modifiers;
+
+    /// Makes `cursor` the current mouse cursor. The returned SDL cursor, if any, has to be
+    /// kept alive for as long as it is shown.
+    fn set_system_cursor(&self, cursor: SystemCursor) -> Result<Option<Cursor>>;
+
+    fn relative_mouse_mode(&self) -> bool;
+
+    fn set_relative_mouse_mode(&self, enabled: bool);
+
+    fn mouse_grab(&self) -> bool;
+
+    fn set_mouse_grab(&self, grabbed: bool);
+
+    fn cursor_visible(&self) -> bool;
+
+    fn show_cursor(&self, visible: bool);
+
+    /// Starts or stops SDL text input (`TextInput`/`TextEditing` events and the IME).
+    fn set_text_input(&self, enabled: bool);
+
+    /// Where the text being edited is, in window coordinates, so the IME can show its
+    /// candidate window next to it.
+    fn set_text_input_rect(&self, rect: sdl2::rect::Rect);
+
+    fn is_minimized(&self) -> bool;
+
+    /// Position of the window in screen coordinates.
+    fn window_position(&self) -> (i32, i32);
+
+    fn set_window_position(&mut self, x: i32, y: i32);
+
+    fn set_window_size(&mut self, width: u32, height: u32) -> Result<()>;
+
+    fn minimum_window_size(&self) -> (u32, u32);
+
+    /// Mouse position in screen coordinates and whether the left button is held down, also
+    /// while the mouse is outside of the window.
+    fn global_mouse_state(&self) -> ((i32, i32), bool);
+
+    /// Applies a viewport command that only changes the window, e.g. its title or size. See
+    /// [`crate::viewport::apply_viewport_command`].
+    fn apply_viewport_command(
+        &mut self,
+        command: &ViewportCommand,
+        pixels_per_point: f32,
+    ) -> Result<()>;
+}
+
+impl Platform for Window {
+    fn window_id(&self) -> u32 {
+        self.id()
+    }
+
+    fn window_size(&self) -> (u32, u32) {
+        self.size()
+    }
+
+    fn clipboard_text(&self) -> Option<String> {
+        self.subsystem().clipboard().clipboard_text().ok()
+    }
+
+    fn set_clipboard_text(&self, text: &str) -> Result<()> {
+        self.subsystem()
+            .clipboard()
+            .set_clipboard_text(text)
+            .map_err(Error::Clipboard)
+    }
+
+    fn modifier_state(&self) -> Modifiers {
+        translate_modifiers(self.subsystem().sdl().keyboard().mod_state())
+    }
+
+    fn set_system_cursor(&self, cursor: SystemCursor) -> Result<Option<Cursor>> {
+        let cursor = Cursor::from_system(cursor).map_err(Error::Cursor)?;
+        cursor.set();
+        Ok(Some(cursor))
+    }
+
+    fn relative_mouse_mode(&self) -> bool {
+        self.subsystem().sdl().mouse().relative_mouse_mode()
+    }
+
+    fn set_relative_mouse_mode(&self, enabled: bool) {
+        self.subsystem()
+            .sdl()
+            .mouse()
+            .set_relative_mouse_mode(enabled);
+    }
+
+    fn mouse_grab(&self) -> bool {
+        Window::mouse_grab(self)
+    }
+
+    fn set_mouse_grab(&self, grabbed: bool) {
+        // `Window::set_mouse_grab` needs `&mut self`, but the grab is window state owned by SDL:
+        let grabbed = if grabbed {
+            sdl2::sys::SDL_bool::SDL_TRUE
+        } else {
+            sdl2::sys::SDL_bool::SDL_FALSE
+        };
+        unsafe { sdl2::sys::SDL_SetWindowMouseGrab(self.raw(), grabbed) };
+    }
+
+    fn cursor_visible(&self) -> bool {
+        self.subsystem().sdl().mouse().is_cursor_showing()
+    }
+
+    fn show_cursor(&self, visible: bool) {
+        self.subsystem().sdl().mouse().show_cursor(visible);
+    }
+
+    fn set_text_input(&self, enabled: bool) {
+        let text_input = self.subsystem().text_input();
+        if enabled {
+            text_input.start();
+        } else {
+            text_input.stop();
+        }
+    }
+
+    fn set_text_input_rect(&self, rect: sdl2::rect::Rect) {
+        self.subsystem().text_input().set_rect(rect);
+    }
+
+    fn is_minimized(&self) -> bool {
+        self.window_flags() & SDL_WindowFlags::SDL_WINDOW_MINIMIZED as u32 != 0
+    }
+
+    fn window_position(&self) -> (i32, i32) {
+        self.position()
+    }
+
+    fn set_window_position(&mut self, x: i32, y: i32) {
+        self.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32) -> Result<()> {
+        self.set_size(width, height)
+            .map_err(|e| Error::Window(e.to_string()))
+    }
+
+    fn minimum_window_size(&self) -> (u32, u32) {
+        self.minimum_size()
+    }
+
+    fn global_mouse_state(&self) -> ((i32, i32), bool) {
+        let (mut x, mut y) = (0, 0);
+        let buttons = unsafe { sdl2::sys::SDL_GetGlobalMouseState(&mut x, &mut y) };
+        (
+            (x, y),
+            buttons & (1 << (sdl2::sys::SDL_BUTTON_LEFT - 1)) != 0,
+        )
+    }
+
+    fn apply_viewport_command(
+        &mut self,
+        command: &ViewportCommand,
+        pixels_per_point: f32,
+    ) -> Result<()> {
+        crate::viewport::apply_viewport_command(self, command, pixels_per_point)
+    }
+}
+
+/// A [`Platform`] that only records what it is asked to do, for tests and headless runs.
+#[derive(Debug)]
+pub struct MockPlatform {
+    pub window_id: u32,
+    pub window_size: Cell<(u32, u32)>,
+    pub clipboard: RefCell<Option<String>>,
+    pub modifiers: Cell<Modifiers>,
+    /// Last cursor set through [`Platform::set_system_cursor`].
+    pub cursor: Cell<Option<SystemCursor>>,
+    pub relative_mouse_mode: Cell<bool>,
+    pub mouse_grab: Cell<bool>,
+    pub cursor_visible: Cell<bool>,
+    pub text_input: Cell<bool>,
+    pub text_input_rect: Cell<Option<sdl2::rect::Rect>>,
+    pub minimized: Cell<bool>,
+    pub window_position: Cell<(i32, i32)>,
+    pub minimum_window_size: Cell<(u32, u32)>,
+    /// Returned by [`Platform::global_mouse_state`].
+    pub global_mouse: Cell<((i32, i32), bool)>,
+    /// Commands passed to [`Platform::apply_viewport_command`], in order.
+    pub viewport_commands: RefCell<Vec<ViewportCommand>>,
+}
+
+impl Default for MockPlatform {
+    fn default() -> Self {
+        Self::new(1, 800, 600)
+    }
+}
+
+impl MockPlatform {
+    pub fn new(window_id: u32, width: u32, height: u32) -> Self {
+        Self {
+            window_id,
+            window_size: Cell::new((width, height)),
+            clipboard: RefCell::new(None),
+            modifiers: Cell::new(Modifiers::default()),
+            cursor: Cell::new(None),
+            relative_mouse_mode: Cell::new(false),
+            mouse_grab: Cell::new(false),
+            cursor_visible: Cell::new(true),
+            text_input: Cell::new(false),
+            text_input_rect: Cell::new(None),
+            minimized: Cell::new(false),
+            window_position: Cell::new((0, 0)),
+            minimum_window_size: Cell::new((0, 0)),
+            global_mouse: Cell::new(((0, 0), false)),
+            viewport_commands: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl Platform for MockPlatform {
+    fn window_id(&self) -> u32 {
+        self.window_id
+    }
+
+    fn window_size(&self) -> (u32, u32) {
+        self.window_size.get()
+    }
+
+    fn clipboard_text(&self) -> Option<String> {
+        self.clipboard.borrow().clone()
+    }
+
+    fn set_clipboard_text(&self, text: &str) -> Result<()> {
+        *self.clipboard.borrow_mut() = Some(text.to_owned());
+        Ok(())
+    }
+
+    fn modifier_state(&self) -> Modifiers {
+        self.modifiers.get()
+    }
+
+    fn set_system_cursor(&self, cursor: SystemCursor) -> Result<Option<Cursor>> {
+        self.cursor.set(Some(cursor));
+        Ok(None)
+    }
+
+    fn relative_mouse_mode(&self) -> bool {
+        self.relative_mouse_mode.get()
+    }
+
+    fn set_relative_mouse_mode(&self, enabled: bool) {
+        self.relative_mouse_mode.set(enabled);
+    }
+
+    fn mouse_grab(&self) -> bool {
+        self.mouse_grab.get()
+    }
+
+    fn set_mouse_grab(&self, grabbed: bool) {
+        self.mouse_grab.set(grabbed);
+    }
+
+    fn cursor_visible(&self) -> bool {
+        self.cursor_visible.get()
+    }
+
+    fn show_cursor(&self, visible: bool) {
+        self.cursor_visible.set(visible);
+    }
+
+    fn set_text_input(&self, enabled: bool) {
+        self.text_input.set(enabled);
+    }
+
+    fn set_text_input_rect(&self, rect: sdl2::rect::Rect) {
+        self.text_input_rect.set(Some(rect));
+    }
+
+    fn is_minimized(&self) -> bool {
+        self.minimized.get()
+    }
+
+    fn window_position(&self) -> (i32, i32) {
+        self.window_position.get()
+    }
+
+    fn set_window_position(&mut self, x: i32, y: i32) {
+        self.window_position.set((x, y));
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32) -> Result<()> {
+        self.window_size.set((width, height));
+        Ok(())
+    }
+
+    fn minimum_window_size(&self) -> (u32, u32) {
+        self.minimum_window_size.get()
+    }
+
+    fn global_mouse_state(&self) -> ((i32, i32), bool) {
+        self.global_mouse.get()
+    }
+
+    fn apply_viewport_command(
+        &mut self,
+        command: &ViewportCommand,
+        _pixels_per_point: f32,
+    ) -> Result<()> {
+        self.viewport_commands.borrow_mut().push(command.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,725 @@
+//! Recording of input sessions and their deterministic replay, e.g. to reproduce bug reports.
+//!
+//! A [`Recorder`] stores every SDL event handed to [`EguiSDL2State`](crate::EguiSDL2State)
+//! and the [`RawInput`] egui got for each frame. A [`Player`] runs the recorded `RawInput`
+//! through a fresh `egui::Context` frame by frame and can check that egui produced the same
+//! shapes as in the recorded session.
+
+use crate::{Error, Result};
+use egui::epaint::Primitive;
+use egui::{ClippedPrimitive, Context, FullOutput, RawInput};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::mouse::{MouseButton, MouseState, MouseWheelDirection};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// A serializable copy of the SDL events this crate translates. Everything else is kept as
+/// its debug description only.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    Quit,
+    WindowClose {
+        window_id: u32,
+    },
+    WindowResized {
+        window_id: u32,
+        width: i32,
+        height: i32,
+    },
+    KeyDown {
+        window_id: u32,
+        keycode: Option<i32>,
+        scancode: Option<i32>,
+        keymod: u16,
+        repeat: bool,
+    },
+    KeyUp {
+        window_id: u32,
+        keycode: Option<i32>,
+        scancode: Option<i32>,
+        keymod: u16,
+        repeat: bool,
+    },
+    TextInput {
+        window_id: u32,
+        text: String,
+    },
+    MouseMotion {
+        window_id: u32,
+        which: u32,
+        mousestate: u32,
+        x: i32,
+        y: i32,
+        xrel: i32,
+        yrel: i32,
+    },
+    MouseButtonDown {
+        window_id: u32,
+        which: u32,
+        button: u8,
+        clicks: u8,
+        x: i32,
+        y: i32,
+    },
+    MouseButtonUp {
+        window_id: u32,
+        which: u32,
+        button: u8,
+        clicks: u8,
+        x: i32,
+        y: i32,
+    },
+    MouseWheel {
+        window_id: u32,
+        which: u32,
+        x: i32,
+        y: i32,
+        direction: u32,
+        precise_x: f32,
+        precise_y: f32,
+        mouse_x: i32,
+        mouse_y: i32,
+    },
+    DropFile {
+        window_id: u32,
+        filename: String,
+    },
+    Other {
+        description: String,
+    },
+}
+
+impl From<&Event> for RecordedEvent {
+    fn from(event: &Event) -> Self {
+        match event {
+            Event::Quit { .. } => Self::Quit,
+            Event::Window {
+                window_id,
+                win_event: WindowEvent::Close,
+                ..
+            } => Self::WindowClose {
+                window_id: *window_id,
+            },
+            Event::Window {
+                window_id,
+                win_event:
+                    WindowEvent::Resized(width, height) | WindowEvent::SizeChanged(width, height),
+                ..
+            } => Self::WindowResized {
+                window_id: *window_id,
+                width: *width,
+                height: *height,
+            },
+            Event::KeyDown {
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+                ..
+            } => Self::KeyDown {
+                window_id: *window_id,
+                keycode: keycode.map(|keycode| keycode.into_i32()),
+                scancode: scancode.map(|scancode| scancode as i32),
+                keymod: keymod.bits(),
+                repeat: *repeat,
+            },
+            Event::KeyUp {
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+                ..
+            } => Self::KeyUp {
+                window_id: *window_id,
+                keycode: keycode.map(|keycode| keycode.into_i32()),
+                scancode: scancode.map(|scancode| scancode as i32),
+                keymod: keymod.bits(),
+                repeat: *repeat,
+            },
+            Event::TextInput {
+                window_id, text, ..
+            } => Self::TextInput {
+                window_id: *window_id,
+                text: text.clone(),
+            },
+            Event::MouseMotion {
+                window_id,
+                which,
+                mousestate,
+                x,
+                y,
+                xrel,
+                yrel,
+                ..
+            } => Self::MouseMotion {
+                window_id: *window_id,
+                which: *which,
+                mousestate: mousestate.to_sdl_state(),
+                x: *x,
+                y: *y,
+                xrel: *xrel,
+                yrel: *yrel,
+            },
+            Event::MouseButtonDown {
+                window_id,
+                which,
+                mouse_btn,
+                clicks,
+                x,
+                y,
+                ..
+            } => Self::MouseButtonDown {
+                window_id: *window_id,
+                which: *which,
+                button: *mouse_btn as u8,
+                clicks: *clicks,
+                x: *x,
+                y: *y,
+            },
+            Event::MouseButtonUp {
+                window_id,
+                which,
+                mouse_btn,
+                clicks,
+                x,
+                y,
+                ..
+            } => Self::MouseButtonUp {
+                window_id: *window_id,
+                which: *which,
+                button: *mouse_btn as u8,
+                clicks: *clicks,
+                x: *x,
+                y: *y,
+            },
+            Event::MouseWheel {
+                window_id,
+                which,
+                x,
+                y,
+                direction,
+                precise_x,
+                precise_y,
+                mouse_x,
+                mouse_y,
+                ..
+            } => Self::MouseWheel {
+                window_id: *window_id,
+                which: *which,
+                x: *x,
+                y: *y,
+                direction: direction.to_ll(),
+                precise_x: *precise_x,
+                precise_y: *precise_y,
+                mouse_x: *mouse_x,
+                mouse_y: *mouse_y,
+            },
+            Event::DropFile {
+                window_id,
+                filename,
+                ..
+            } => Self::DropFile {
+                window_id: *window_id,
+                filename: filename.clone(),
+            },
+            other => Self::Other {
+                description: format!("{other:?}"),
+            },
+        }
+    }
+}
+
+impl RecordedEvent {
+    /// Rebuilds the SDL event with the given timestamp, `None` for [`RecordedEvent::Other`].
+    pub fn to_event(&self, timestamp: u32) -> Option<Event> {
+        let key = |keycode: &Option<i32>, scancode: &Option<i32>, keymod: &u16| {
+            (
+                keycode.and_then(Keycode::from_i32),
+                scancode.and_then(Scancode::from_i32),
+                Mod::from_bits_truncate(*keymod),
+            )
+        };
+        Some(match self {
+            Self::Quit => Event::Quit { timestamp },
+            Self::WindowClose { window_id } => Event::Window {
+                timestamp,
+                window_id: *window_id,
+                win_event: WindowEvent::Close,
+            },
+            Self::WindowResized {
+                window_id,
+                width,
+                height,
+            } => Event::Window {
+                timestamp,
+                window_id: *window_id,
+                win_event: WindowEvent::Resized(*width, *height),
+            },
+            Self::KeyDown {
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+            } => {
+                let (keycode, scancode, keymod) = key(keycode, scancode, keymod);
+                Event::KeyDown {
+                    timestamp,
+                    window_id: *window_id,
+                    keycode,
+                    scancode,
+                    keymod,
+                    repeat: *repeat,
+                }
+            }
+            Self::KeyUp {
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+            } => {
+                let (keycode, scancode, keymod) = key(keycode, scancode, keymod);
+                Event::KeyUp {
+                    timestamp,
+                    window_id: *window_id,
+                    keycode,
+                    scancode,
+                    keymod,
+                    repeat: *repeat,
+                }
+            }
+            Self::TextInput { window_id, text } => Event::TextInput {
+                timestamp,
+                window_id: *window_id,
+                text: text.clone(),
+            },
+            Self::MouseMotion {
+                window_id,
+                which,
+                mousestate,
+                x,
+                y,
+                xrel,
+                yrel,
+            } => Event::MouseMotion {
+                timestamp,
+                window_id: *window_id,
+                which: *which,
+                mousestate: MouseState::from_sdl_state(*mousestate),
+                x: *x,
+                y: *y,
+                xrel: *xrel,
+                yrel: *yrel,
+            },
+            Self::MouseButtonDown {
+                window_id,
+                which,
+                button,
+                clicks,
+                x,
+                y,
+            } => Event::MouseButtonDown {
+                timestamp,
+                window_id: *window_id,
+                which: *which,
+                mouse_btn: MouseButton::from_ll(*button),
+                clicks: *clicks,
+                x: *x,
+                y: *y,
+            },
+            Self::MouseButtonUp {
+                window_id,
+                which,
+                button,
+                clicks,
+                x,
+                y,
+            } => Event::MouseButtonUp {
+                timestamp,
+                window_id: *window_id,
+                which: *which,
+                mouse_btn: MouseButton::from_ll(*button),
+                clicks: *clicks,
+                x: *x,
+                y: *y,
+            },
+            Self::MouseWheel {
+                window_id,
+                which,
+                x,
+                y,
+                direction,
+                precise_x,
+                precise_y,
+                mouse_x,
+                mouse_y,
+            } => Event::MouseWheel {
+                timestamp,
+                window_id: *window_id,
+                which: *which,
+                x: *x,
+                y: *y,
+                direction: MouseWheelDirection::from_ll(*direction),
+                precise_x: *precise_x,
+                precise_y: *precise_y,
+                mouse_x: *mouse_x,
+                mouse_y: *mouse_y,
+            },
+            Self::DropFile {
+                window_id,
+                filename,
+            } => Event::DropFile {
+                timestamp,
+                window_id: *window_id,
+                filename: filename.clone(),
+            },
+            Self::Other { .. } => return None,
+        })
+    }
+}
+
+/// An SDL event with its timestamp in milliseconds since SDL was initialized.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedEvent {
+    pub timestamp: u32,
+    pub event: RecordedEvent,
+}
+
+/// Everything recorded for one frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// The SDL events that arrived since the previous frame.
+    pub events: Vec<TimedEvent>,
+    /// The input egui got for this frame.
+    pub raw_input: RawInput,
+    /// Hash of the shapes egui produced, see [`Recorder::record_output`].
+    pub shapes_hash: Option<u64>,
+}
+
+/// A recorded input session, stored as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| record_error(path, &e))?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| record_error(path, &e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| record_error(path, &e))?;
+        serde_json::to_writer(BufWriter::new(file), self).map_err(|e| record_error(path, &e))
+    }
+
+    /// Like [`Recording::load`], from any reader.
+    pub fn read(reader: impl Read) -> Result<Self> {
+        serde_json::from_reader(reader).map_err(|e| Error::Record(e.to_string()))
+    }
+
+    /// Like [`Recording::save`], to any writer.
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        serde_json::to_writer(writer, self).map_err(|e| Error::Record(e.to_string()))
+    }
+}
+
+fn record_error(path: &Path, error: &dyn std::fmt::Display) -> Error {
+    Error::Record(format!("{}: {error}", path.display()))
+}
+
+/// A stable hash of what `full_output` draws, tessellated by `ctx`. `None` if it contains
+/// paint callbacks, which can't be compared.
+pub fn shapes_hash(ctx: &Context, full_output: &FullOutput) -> Option<u64> {
+    let primitives = ctx.tessellate(full_output.shapes.clone(), full_output.pixels_per_point);
+    // FNV-1a, identical across runs and platforms:
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    for ClippedPrimitive {
+        clip_rect,
+        primitive,
+    } in &primitives
+    {
+        let Primitive::Mesh(mesh) = primitive else {
+            return None;
+        };
+        for value in [clip_rect.min, clip_rect.max] {
+            write(&value.x.to_le_bytes());
+            write(&value.y.to_le_bytes());
+        }
+        write(&serde_json::to_vec(mesh).ok()?);
+    }
+    Some(hash)
+}
+
+/// Records a session. In the event loop, call [`Recorder::record_event`] next to
+/// `sdl2_input_to_egui`, pass the frame's input through [`Recorder::record_frame`] and,
+/// to allow checking the replay, hand the output to [`Recorder::record_output`].
+#[derive(Debug, Default)]
+pub struct Recorder {
+    recording: Recording,
+    pending: Vec<TimedEvent>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_event(&mut self, event: &Event) {
+        self.pending.push(TimedEvent {
+            timestamp: event.get_timestamp(),
+            event: event.into(),
+        });
+    }
+
+    /// Records the input of a frame and returns it, e.g.
+    /// `ctx.run(recorder.record_frame(state.raw_input.take()), ...)`.
+    pub fn record_frame(&mut self, raw_input: RawInput) -> RawInput {
+        self.recording.frames.push(RecordedFrame {
+            events: std::mem::take(&mut self.pending),
+            raw_input: raw_input.clone(),
+            shapes_hash: None,
+        });
+        raw_input
+    }
+
+    /// Remembers what the last recorded frame drew, for [`Player::play_frame`] to compare.
+    pub fn record_output(&mut self, ctx: &Context, full_output: &FullOutput) {
+        if let Some(frame) = self.recording.frames.last_mut() {
+            frame.shapes_hash = shapes_hash(ctx, full_output);
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.recording.save(path)
+    }
+}
+
+/// Replays a [`Recording`] frame by frame.
+///
+/// The context should be in the same state as the recorded one at the start of the session,
+/// usually a fresh `Context` with the same style and fonts, and `run_ui` should build the same
+/// UI.
+#[derive(Debug)]
+pub struct Player {
+    recording: Recording,
+    next_frame: usize,
+    /// Compare the shapes of every frame against the recorded hash.
+    pub verify_shapes: bool,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+            verify_shapes: false,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Recording::load(path)?))
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Whether all frames have been played.
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    /// Runs the next recorded frame, `None` when the recording is over. With
+    /// [`Player::verify_shapes`], returns an error if the shapes differ from the recording.
+    pub fn play_frame(
+        &mut self,
+        ctx: &Context,
+        run_ui: impl FnMut(&Context),
+    ) -> Option<Result<FullOutput>> {
+        let frame = self.recording.frames.get(self.next_frame)?;
+        let index = self.next_frame;
+        self.next_frame += 1;
+        let full_output = ctx.run(frame.raw_input.clone(), run_ui);
+        if self.verify_shapes {
+            if let Some(expected) = frame.shapes_hash {
+                if shapes_hash(ctx, &full_output) != Some(expected) {
+                    return Some(Err(Error::Record(format!(
+                        "frame {index}: shapes differ from the recording"
+                    ))));
+                }
+            }
+        }
+        Some(Ok(full_output))
+    }
+
+    /// Plays all remaining frames, stopping at the first mismatch.
+    pub fn play_all(&mut self, ctx: &Context, mut run_ui: impl FnMut(&Context)) -> Result<()> {
+        while let Some(result) = self.play_frame(ctx, &mut run_ui) {
+            result?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::MockPlatform;
+    use crate::simulate::InputSimulator;
+    use crate::EguiSDL2State;
+    use egui::pos2;
+    use std::cell::Cell;
+
+    #[test]
+    fn events_round_trip() {
+        let mut input = InputSimulator::new(1, 1.0);
+        let mut events = Vec::new();
+        events.extend(input.drag(&[pos2(10.0, 20.0), pos2(30.0, 40.0)]));
+        events.extend(input.click_button(pos2(5.0, 5.0), MouseButton::Right));
+        events.extend(input.type_text("hi"));
+        events.extend(input.shortcut(Mod::LCTRLMOD, Keycode::C));
+        events.extend(input.scroll(1, -2));
+        // `DropBegin` and `DropComplete` are not translated, so only kept as `Other`:
+        let drop = input.drop_file("file.txt").into_iter();
+        events.extend(drop.filter(|event| matches!(event, Event::DropFile { .. })));
+        events.push(Event::MouseWheel {
+            timestamp: 7,
+            window_id: 1,
+            which: 3,
+            x: 0,
+            y: 1,
+            direction: MouseWheelDirection::Flipped,
+            precise_x: 0.0,
+            precise_y: 1.5,
+            mouse_x: 2,
+            mouse_y: 3,
+        });
+        events.push(Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Resized(640, 480),
+        });
+        events.push(Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Close,
+        });
+        events.push(Event::Quit { timestamp: 0 });
+
+        for event in &events {
+            let recorded = RecordedEvent::from(event);
+            assert!(
+                !matches!(recorded, RecordedEvent::Other { .. }),
+                "{event:?}"
+            );
+            let replayed = recorded.to_event(event.get_timestamp());
+            assert_eq!(replayed.as_ref(), Some(event));
+        }
+    }
+
+    /// A button that counts its clicks.
+    fn counter_ui<'a>(
+        label: &'static str,
+        clicks: &'a Cell<u32>,
+        button_rect: &'a Cell<egui::Rect>,
+    ) -> impl FnMut(&Context) + 'a {
+        move |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.button(label);
+                if response.clicked() {
+                    clicks.set(clicks.get() + 1);
+                }
+                button_rect.set(response.rect);
+                ui.label(format!("clicked {} times", clicks.get()));
+            });
+        }
+    }
+
+    fn record_session() -> (Recording, u32) {
+        let platform = MockPlatform::new(1, 400, 300);
+        let mut state = EguiSDL2State::new(400, 300, 1.0);
+        let mut input = InputSimulator::new(1, 1.0);
+        let ctx = Context::default();
+        let clicks = Cell::new(0);
+        let button_rect = Cell::new(egui::Rect::NOTHING);
+        let mut ui = counter_ui("Click me", &clicks, &button_rect);
+        let mut recorder = Recorder::new();
+
+        let mut run_frame = |state: &mut EguiSDL2State, events: Vec<Event>| {
+            for event in &events {
+                recorder.record_event(event);
+                state.sdl2_input_to_egui(&platform, event);
+            }
+            let raw_input = recorder.record_frame(state.raw_input.take());
+            let full_output = ctx.run(raw_input, &mut ui);
+            recorder.record_output(&ctx, &full_output);
+        };
+        run_frame(&mut state, Vec::new());
+        let center = button_rect.get().center();
+        run_frame(&mut state, input.mouse_move(center));
+        run_frame(&mut state, input.click(center));
+        run_frame(&mut state, input.click(center));
+        run_frame(&mut state, Vec::new());
+        (recorder.into_recording(), clicks.get())
+    }
+
+    #[test]
+    fn replay_matches_the_recording() {
+        let (recording, recorded_clicks) = record_session();
+        assert_eq!(recorded_clicks, 2);
+        assert!(recording
+            .frames
+            .iter()
+            .all(|frame| frame.shapes_hash.is_some()));
+
+        let mut json = Vec::new();
+        recording.write(&mut json).unwrap();
+        let loaded = Recording::read(json.as_slice()).unwrap();
+        assert_eq!(loaded, recording);
+
+        let clicks = Cell::new(0);
+        let button_rect = Cell::new(egui::Rect::NOTHING);
+        let mut player = Player::new(loaded);
+        player.verify_shapes = true;
+        let ctx = Context::default();
+        player
+            .play_all(&ctx, counter_ui("Click me", &clicks, &button_rect))
+            .unwrap();
+        assert!(player.is_finished());
+        assert_eq!(clicks.get(), recorded_clicks);
+    }
+
+    #[test]
+    fn changed_ui_fails_verification() {
+        let (recording, _) = record_session();
+        let clicks = Cell::new(0);
+        let button_rect = Cell::new(egui::Rect::NOTHING);
+        let mut player = Player::new(recording);
+        player.verify_shapes = true;
+        let ctx = Context::default();
+        let result = player.play_all(&ctx, counter_ui("Press me", &clicks, &button_rect));
+        assert!(matches!(result, Err(Error::Record(_))));
+    }
+}
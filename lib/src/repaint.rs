@@ -0,0 +1,110 @@
+use crate::{Error, Result};
+use sdl2::event::{Event, EventSender};
+use sdl2::EventSubsystem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// No wake-up event is in the queue.
+const NONE_PENDING: u64 = u64::MAX;
+
+/// Wakes a blocked SDL event loop when egui requests a repaint from any thread, see
+/// [`crate::EguiSDL2State::enable_repaint_wakeup`].
+#[derive(Debug)]
+pub(crate) struct RepaintWakeup {
+    event_type: u32,
+    /// Shortest delay in milliseconds requested since the last wake-up event was received.
+    pending: Arc<AtomicU64>,
+}
+
+impl RepaintWakeup {
+    /// Registers the SDL user event and makes `ctx` push it whenever a repaint is requested.
+    pub(crate) fn install(
+        ctx: &egui::Context,
+        events: &EventSubsystem,
+        window_id: u32,
+    ) -> Result<Self> {
+        // only registers a new event type number with SDL:
+        let event_type = unsafe { events.register_event() }.map_err(Error::Sdl)?;
+        let pending = Arc::new(AtomicU64::new(NONE_PENDING));
+        let sender: EventSender = events.event_sender();
+        let callback_pending = pending.clone();
+        ctx.set_request_repaint_callback(move |info| {
+            if !request(&callback_pending, info.delay) {
+                return;
+            }
+            let event = Event::User {
+                timestamp: 0,
+                window_id,
+                type_: event_type,
+                code: 0,
+                data1: std::ptr::null_mut(),
+                data2: std::ptr::null_mut(),
+            };
+            if let Err(err) = sender.push_event(event) {
+                warn!("failed to push repaint event: {err}");
+            }
+        });
+        Ok(Self {
+            event_type,
+            pending,
+        })
+    }
+
+    pub(crate) fn is_wakeup(&self, event: &Event) -> bool {
+        matches!(event, Event::User { type_, .. } if *type_ == self.event_type)
+    }
+
+    /// If `event` is a wake-up event, returns the delay of the repaint it stands for, or
+    /// `None` if an earlier wake-up event already delivered it.
+    pub(crate) fn receive(&self, event: &Event) -> Option<Duration> {
+        if !self.is_wakeup(event) {
+            return None;
+        }
+        match self.pending.swap(NONE_PENDING, Ordering::AcqRel) {
+            NONE_PENDING => None,
+            delay => Some(Duration::from_millis(delay)),
+        }
+    }
+}
+
+/// Records a repaint request, returns whether a wake-up event has to be pushed for it.
+fn request(pending: &AtomicU64, delay: Duration) -> bool {
+    let delay = delay.as_millis().min(NONE_PENDING as u128 - 1) as u64;
+    // one event in the queue is enough, unless this repaint is due earlier:
+    pending.fetch_min(delay, Ordering::AcqRel) > delay
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wakeup_event(type_: u32) -> Event {
+        Event::User {
+            timestamp: 0,
+            window_id: 1,
+            type_,
+            code: 0,
+            data1: std::ptr::null_mut(),
+            data2: std::ptr::null_mut(),
+        }
+    }
+
+    #[test]
+    fn two_wakeups_deliver_one_repaint() {
+        let wakeup = RepaintWakeup {
+            event_type: 0x8000,
+            pending: Arc::new(AtomicU64::new(NONE_PENDING)),
+        };
+        assert!(request(&wakeup.pending, Duration::from_millis(100)));
+        // a later repaint needs no event of its own, an earlier one does:
+        assert!(!request(&wakeup.pending, Duration::from_millis(200)));
+        assert!(request(&wakeup.pending, Duration::ZERO));
+
+        let event = wakeup_event(0x8000);
+        assert_eq!(wakeup.receive(&event), Some(Duration::ZERO));
+        assert!(wakeup.is_wakeup(&event));
+        assert_eq!(wakeup.receive(&event), None);
+        assert_eq!(wakeup.receive(&wakeup_event(0x8001)), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! A complete application runner in the spirit of `eframe`: implement [`App`], pick a
+//! [`Painter`] and hand both to [`run_native`].
+
+use crate::{viewport, Error, FrameClock, Result};
+use egui::{Context, ViewportBuilder, ViewportId};
+use sdl2::video::Window;
+use std::time::{Duration, Instant};
+
+pub use crate::painter::Painter;
+
+/// An application run by [`run_native`].
+pub trait App {
+    /// Called every frame to build the UI.
+    fn update(&mut self, ctx: &Context, frame: &mut Frame<'_>);
+
+    /// Called once before the runner returns, after the last [`App::save`].
+    fn on_exit(&mut self) {}
+
+    /// Called every [`App::auto_save_interval`] and before exiting, to persist state.
+    fn save(&mut self) {}
+
+    fn auto_save_interval(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+}
+
+/// What the runner exposes to [`App::update`] besides the egui context.
+pub struct Frame<'a> {
+    window: &'a Window,
+}
+
+impl Frame<'_> {
+    /// The SDL window the app is shown in. Change it through `egui::ViewportCommand`s.
+    pub fn window(&self) -> &Window {
+        self.window
+    }
+}
+
+/// Passed to the app creator of [`run_native`].
+pub struct CreationContext<'a> {
+    pub egui_ctx: &'a Context,
+    pub window: &'a Window,
+}
+
+/// Settings for [`run_native`].
+#[derive(Clone, Debug)]
+pub struct NativeOptions {
+    /// Initial settings of the window.
+    pub viewport: ViewportBuilder,
+    /// Only run a frame on input or when egui asks for a repaint. Otherwise frames are run
+    /// continuously, which suits games.
+    pub reactive: bool,
+    /// Create the window with OpenGL support, as needed by the `glow` painter.
+    pub opengl: bool,
+    /// SDL window coordinates per point, see [`viewport::create_window_with`].
+    pub pixels_per_point: f32,
+}
+
+impl Default for NativeOptions {
+    fn default() -> Self {
+        Self {
+            viewport: ViewportBuilder::default(),
+            reactive: true,
+            opengl: false,
+            pixels_per_point: 1.0,
+        }
+    }
+}
+
+/// Initializes SDL, creates the window and runs `app` until it is closed.
+///
+/// `create_painter` receives the new window and returns the painter that owns it.
+pub fn run_native<P: Painter>(
+    app_name: &str,
+    mut options: NativeOptions,
+    create_painter: impl FnOnce(Window) -> Result<P>,
+    app_creator: impl FnOnce(&CreationContext<'_>) -> Box<dyn App>,
+) -> Result<()> {
+    let sdl = sdl2::init().map_err(Error::Sdl)?;
+    let video = sdl.video().map_err(Error::Sdl)?;
+    let events = sdl.event().map_err(Error::Sdl)?;
+    let timer = sdl.timer().map_err(Error::Sdl)?;
+    let mut event_pump = sdl.event_pump().map_err(Error::Sdl)?;
+
+    if options.viewport.title.is_none() {
+        options.viewport.title = Some(app_name.to_owned());
+    }
+    let (window, mut state) = viewport::create_window_with(
+        &video,
+        &options.viewport,
+        options.pixels_per_point,
+        |builder| {
+            if options.opengl {
+                builder.opengl();
+            }
+        },
+    )?;
+    let mut painter = create_painter(window)?;
+    state.raw_input.max_texture_side = painter.max_texture_side();
+
+    let egui_ctx = Context::default();
+    state.enable_repaint_wakeup(&egui_ctx, &events, painter.window())?;
+    let mut app = app_creator(&CreationContext {
+        egui_ctx: &egui_ctx,
+        window: painter.window(),
+    });
+    let mut frame_clock = FrameClock::new(timer).with_refresh_rate_of(painter.window());
+    let mut last_save = Instant::now();
+
+    let result = loop {
+        if options.reactive {
+            let event = frame_clock.wait_for_event(&state, painter.window(), &mut event_pump);
+            if let Some(event) = event {
+                state.sdl2_input_to_egui(painter.window(), &event);
+            }
+        }
+        for event in event_pump.poll_iter() {
+            state.sdl2_input_to_egui(painter.window(), &event);
+        }
+        frame_clock.tick(&mut state);
+
+        let full_output = egui_ctx.run(state.raw_input.take(), |ctx| {
+            app.update(
+                ctx,
+                &mut Frame {
+                    window: painter.window(),
+                },
+            );
+        });
+        state.update_input_capture(&egui_ctx);
+        state.update_repaint_delay(&full_output);
+        state.process_output(painter.window(), &full_output.platform_output);
+        if let Some(viewport_output) = full_output.viewport_output.get(&ViewportId::ROOT) {
+            state.process_viewport_commands(painter.window_mut(), &viewport_output.commands);
+        }
+        if state.should_close() {
+            break Ok(());
+        }
+
+        let clipped_primitives =
+            egui_ctx.tessellate(full_output.shapes, full_output.pixels_per_point);
+        // Still save the app's state and let it clean up if painting fails:
+        if let Err(e) = state.paint(
+            &mut painter,
+            full_output.pixels_per_point,
+            &full_output.textures_delta,
+            &clipped_primitives,
+        ) {
+            break Err(e);
+        }
+
+        if last_save.elapsed() >= app.auto_save_interval() {
+            app.save();
+            last_save = Instant::now();
+        }
+    };
+
+    app.save();
+    app.on_exit();
+    result
+}
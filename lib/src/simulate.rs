@@ -0,0 +1,391 @@
+//! Synthetic input for tests and UI automation.
+//!
+//! [`InputSimulator`] turns high-level actions into the SDL events a real user would cause.
+//! The events can be pushed onto the SDL queue with [`push_events`] or handed straight to
+//! [`EguiSDL2State::sdl2_input_to_egui`] with [`feed`].
+
+use crate::{EguiSDL2State, Error, Platform, Result};
+use egui::Pos2;
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::mouse::{MouseButton, MouseState, MouseWheelDirection};
+use sdl2::{sys, EventSubsystem};
+use std::ffi::CString;
+use std::path::Path;
+
+/// Modifier flags and the key that produces them, in the order they are pressed.
+const MODIFIER_KEYS: [(Mod, Keycode); 4] = [
+    (Mod::LCTRLMOD, Keycode::LCtrl),
+    (Mod::LSHIFTMOD, Keycode::LShift),
+    (Mod::LALTMOD, Keycode::LAlt),
+    (Mod::LGUIMOD, Keycode::LGui),
+];
+
+/// Builds the SDL event sequences for clicks, drags, typing, shortcuts, scrolling and file
+/// drops on one window. Positions are in points.
+///
+/// Remembers the mouse position between actions, so relative motion is reported correctly.
+#[derive(Clone, Debug)]
+pub struct InputSimulator {
+    pub window_id: u32,
+    pub pixels_per_point: f32,
+    mouse_pos: Pos2,
+}
+
+impl InputSimulator {
+    pub fn new(window_id: u32, pixels_per_point: f32) -> Self {
+        Self {
+            window_id,
+            pixels_per_point,
+            mouse_pos: Pos2::ZERO,
+        }
+    }
+
+    fn to_window(&self, pos: Pos2) -> (i32, i32) {
+        (
+            (pos.x * self.pixels_per_point).round() as i32,
+            (pos.y * self.pixels_per_point).round() as i32,
+        )
+    }
+
+    /// Moves the mouse to `pos` while `buttons` (an SDL button mask) are held.
+    fn motion(&mut self, pos: Pos2, buttons: u32) -> Event {
+        let (x, y) = self.to_window(pos);
+        let (old_x, old_y) = self.to_window(self.mouse_pos);
+        self.mouse_pos = pos;
+        Event::MouseMotion {
+            timestamp: 0,
+            window_id: self.window_id,
+            which: 0,
+            mousestate: MouseState::from_sdl_state(buttons),
+            x,
+            y,
+            xrel: x - old_x,
+            yrel: y - old_y,
+        }
+    }
+
+    fn button(&self, mouse_btn: MouseButton, pressed: bool) -> Event {
+        let (x, y) = self.to_window(self.mouse_pos);
+        if pressed {
+            Event::MouseButtonDown {
+                timestamp: 0,
+                window_id: self.window_id,
+                which: 0,
+                mouse_btn,
+                clicks: 1,
+                x,
+                y,
+            }
+        } else {
+            Event::MouseButtonUp {
+                timestamp: 0,
+                window_id: self.window_id,
+                which: 0,
+                mouse_btn,
+                clicks: 1,
+                x,
+                y,
+            }
+        }
+    }
+
+    fn key(&self, keycode: Keycode, keymod: Mod, pressed: bool) -> Event {
+        let scancode = Scancode::from_keycode(keycode);
+        if pressed {
+            Event::KeyDown {
+                timestamp: 0,
+                window_id: self.window_id,
+                keycode: Some(keycode),
+                scancode,
+                keymod,
+                repeat: false,
+            }
+        } else {
+            Event::KeyUp {
+                timestamp: 0,
+                window_id: self.window_id,
+                keycode: Some(keycode),
+                scancode,
+                keymod,
+                repeat: false,
+            }
+        }
+    }
+
+    /// Moves the mouse to `pos`.
+    pub fn mouse_move(&mut self, pos: Pos2) -> Vec<Event> {
+        vec![self.motion(pos, 0)]
+    }
+
+    /// Moves to `pos` and clicks the left mouse button.
+    pub fn click(&mut self, pos: Pos2) -> Vec<Event> {
+        self.click_button(pos, MouseButton::Left)
+    }
+
+    /// Moves to `pos` and clicks `button`.
+    pub fn click_button(&mut self, pos: Pos2, button: MouseButton) -> Vec<Event> {
+        vec![
+            self.motion(pos, 0),
+            self.button(button, true),
+            self.button(button, false),
+        ]
+    }
+
+    /// Presses the left mouse button at the first point of `path`, moves along the rest and
+    /// releases it at the last one.
+    pub fn drag(&mut self, path: &[Pos2]) -> Vec<Event> {
+        let Some((&start, rest)) = path.split_first() else {
+            return Vec::new();
+        };
+        let left = 1 << (sdl2::sys::SDL_BUTTON_LEFT - 1);
+        let mut events = vec![self.motion(start, 0), self.button(MouseButton::Left, true)];
+        events.extend(rest.iter().map(|&pos| self.motion(pos, left)));
+        events.push(self.button(MouseButton::Left, false));
+        events
+    }
+
+    /// Types `text`, one text input event per character as with a keyboard.
+    pub fn type_text(&mut self, text: &str) -> Vec<Event> {
+        text.chars()
+            .map(|c| Event::TextInput {
+                timestamp: 0,
+                window_id: self.window_id,
+                text: c.to_string(),
+            })
+            .collect()
+    }
+
+    /// Presses and releases `keycode`.
+    pub fn key_press(&mut self, keycode: Keycode) -> Vec<Event> {
+        self.shortcut(Mod::NOMOD, keycode)
+    }
+
+    /// Holds the left modifier keys in `keymod` (ctrl, shift, alt, gui) while pressing
+    /// `keycode`, e.g. `shortcut(Mod::LCTRLMOD, Keycode::C)`.
+    pub fn shortcut(&mut self, keymod: Mod, keycode: Keycode) -> Vec<Event> {
+        let modifiers: Vec<_> = MODIFIER_KEYS
+            .iter()
+            .filter(|(flag, _)| keymod.contains(*flag))
+            .collect();
+        let mut events = Vec::new();
+        let mut held = Mod::NOMOD;
+        for (flag, key) in &modifiers {
+            held |= *flag;
+            events.push(self.key(*key, held, true));
+        }
+        events.push(self.key(keycode, keymod, true));
+        events.push(self.key(keycode, keymod, false));
+        for (flag, key) in modifiers.iter().rev() {
+            held.remove(*flag);
+            events.push(self.key(*key, held, false));
+        }
+        events
+    }
+
+    /// Scrolls by `x`, `y` wheel steps at the current mouse position. Positive `y` scrolls up.
+    pub fn scroll(&mut self, x: i32, y: i32) -> Vec<Event> {
+        let (mouse_x, mouse_y) = self.to_window(self.mouse_pos);
+        vec![Event::MouseWheel {
+            timestamp: 0,
+            window_id: self.window_id,
+            which: 0,
+            x,
+            y,
+            direction: MouseWheelDirection::Normal,
+            precise_x: x as f32,
+            precise_y: y as f32,
+            mouse_x,
+            mouse_y,
+        }]
+    }
+
+    /// Drops the file at `path` on the window.
+    pub fn drop_file(&mut self, path: impl AsRef<Path>) -> Vec<Event> {
+        vec![
+            Event::DropBegin {
+                timestamp: 0,
+                window_id: self.window_id,
+            },
+            Event::DropFile {
+                timestamp: 0,
+                window_id: self.window_id,
+                filename: path.as_ref().to_string_lossy().into_owned(),
+            },
+            Event::DropComplete {
+                timestamp: 0,
+                window_id: self.window_id,
+            },
+        ]
+    }
+}
+
+/// An event in the form it is pushed onto the SDL queue.
+enum QueuedEvent {
+    /// Events `EventSubsystem::push_event` accepts.
+    Supported(Event),
+    /// Text input and file drops, which have to be built by hand.
+    Raw(sys::SDL_Event),
+    /// A file drop, whose name is handed over to SDL when it is pushed.
+    DropFile {
+        timestamp: u32,
+        window_id: u32,
+        filename: CString,
+    },
+}
+
+impl QueuedEvent {
+    fn new(event: Event) -> Result<Self> {
+        let drop_event = |type_: sys::SDL_EventType, timestamp, window_id| {
+            let mut raw: sys::SDL_Event = unsafe { std::mem::zeroed() };
+            raw.drop = sys::SDL_DropEvent {
+                type_: type_ as u32,
+                timestamp,
+                file: std::ptr::null_mut(),
+                windowID: window_id,
+            };
+            Self::Raw(raw)
+        };
+        Ok(match event {
+            Event::Quit { .. }
+            | Event::Window { .. }
+            | Event::KeyDown { .. }
+            | Event::KeyUp { .. }
+            | Event::MouseMotion { .. }
+            | Event::MouseButtonDown { .. }
+            | Event::MouseButtonUp { .. }
+            | Event::MouseWheel { .. }
+            | Event::User { .. } => Self::Supported(event),
+            Event::TextInput {
+                timestamp,
+                window_id,
+                text,
+            } => {
+                let mut raw_text = [0; 32];
+                // the text is NUL terminated:
+                if text.len() >= raw_text.len() || text.contains('\0') {
+                    return Err(Error::Sdl(format!(
+                        "text input {text:?} does not fit into one SDL event"
+                    )));
+                }
+                for (raw, byte) in raw_text.iter_mut().zip(text.bytes()) {
+                    *raw = byte as std::ffi::c_char;
+                }
+                let mut raw: sys::SDL_Event = unsafe { std::mem::zeroed() };
+                raw.text = sys::SDL_TextInputEvent {
+                    type_: sys::SDL_EventType::SDL_TEXTINPUT as u32,
+                    timestamp,
+                    windowID: window_id,
+                    text: raw_text,
+                };
+                Self::Raw(raw)
+            }
+            Event::DropBegin {
+                timestamp,
+                window_id,
+            } => drop_event(sys::SDL_EventType::SDL_DROPBEGIN, timestamp, window_id),
+            Event::DropComplete {
+                timestamp,
+                window_id,
+            } => drop_event(sys::SDL_EventType::SDL_DROPCOMPLETE, timestamp, window_id),
+            Event::DropFile {
+                timestamp,
+                window_id,
+                filename,
+            } => Self::DropFile {
+                timestamp,
+                window_id,
+                filename: CString::new(filename).map_err(|e| Error::Sdl(e.to_string()))?,
+            },
+            other => {
+                return Err(Error::Sdl(format!(
+                    "{other:?} can't be pushed onto the SDL queue"
+                )))
+            }
+        })
+    }
+
+    fn push(self, event_subsystem: &EventSubsystem) -> Result<()> {
+        let mut raw = match self {
+            Self::Supported(event) => return event_subsystem.push_event(event).map_err(Error::Sdl),
+            Self::Raw(raw) => raw,
+            Self::DropFile {
+                timestamp,
+                window_id,
+                filename,
+            } => {
+                // the receiver frees the name with `SDL_free`:
+                let bytes = filename.as_bytes_with_nul();
+                let file = unsafe { sys::SDL_malloc(bytes.len()) } as *mut std::ffi::c_char;
+                if file.is_null() {
+                    return Err(Error::Sdl("out of memory".into()));
+                }
+                unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr().cast(), file, bytes.len()) };
+                let mut raw: sys::SDL_Event = unsafe { std::mem::zeroed() };
+                raw.drop = sys::SDL_DropEvent {
+                    type_: sys::SDL_EventType::SDL_DROPFILE as u32,
+                    timestamp,
+                    file,
+                    windowID: window_id,
+                };
+                raw
+            }
+        };
+        if unsafe { sys::SDL_PushEvent(&mut raw) } == 1 {
+            return Ok(());
+        }
+        let error = sdl2::get_error();
+        // nobody will receive the event, so the file name is still ours:
+        if unsafe { raw.type_ } == sys::SDL_EventType::SDL_DROPFILE as u32 {
+            unsafe { sys::SDL_free(raw.drop.file.cast()) };
+        }
+        Err(Error::Sdl(error))
+    }
+}
+
+/// Pushes `events` onto the SDL event queue, to be picked up by the application's event loop.
+///
+/// Besides the events `EventSubsystem::push_event` accepts, text input and file drops are
+/// supported. All events are checked before the first one is pushed, so a sequence is not cut
+/// short by an unsupported event.
+pub fn push_events(
+    event_subsystem: &EventSubsystem,
+    events: impl IntoIterator<Item = Event>,
+) -> Result<()> {
+    let events = events
+        .into_iter()
+        .map(QueuedEvent::new)
+        .collect::<Result<Vec<_>>>()?;
+    for event in events {
+        event.push(event_subsystem)?;
+    }
+    Ok(())
+}
+
+/// Hands `events` directly to `state`, bypassing the SDL queue.
+pub fn feed(
+    state: &mut EguiSDL2State,
+    window: &(impl Platform + ?Sized),
+    events: impl IntoIterator<Item = Event>,
+) {
+    for event in events {
+        state.sdl2_input_to_egui(window, &event);
+    }
+}
+
+/// Finds the widget labelled `label` in the AccessKit tree of a frame and returns its
+/// rectangle in points. Requires `Context::enable_accesskit`.
+#[cfg(feature = "accesskit")]
+pub fn find_by_label(output: &egui::PlatformOutput, label: &str) -> Option<egui::Rect> {
+    let update = output.accesskit_update.as_ref()?;
+    update.nodes.iter().find_map(|(_, node)| {
+        if node.label() != Some(label) {
+            return None;
+        }
+        let bounds = node.bounds()?;
+        Some(egui::Rect::from_min_max(
+            egui::pos2(bounds.x0 as f32, bounds.y0 as f32),
+            egui::pos2(bounds.x1 as f32, bounds.y1 as f32),
+        ))
+    })
+}
@@ -0,0 +1,342 @@
+//! Headless rendering of egui for snapshot tests.
+//!
+//! A [`Harness`] runs an egui UI through [`EguiSDL2State`] on a [`MockPlatform`], so no display
+//! or SDL video driver is needed, renders each frame with the
+//! [`SoftwareRenderer`](crate::painter::SoftwareRenderer) and compares the result to PNG files.
+//!
+//! Set the `UPDATE_SNAPSHOTS` environment variable to write the current images as the new
+//! expected ones.
+
+use crate::painter::SoftwareRenderer;
+use crate::simulate::InputSimulator;
+use crate::{EguiSDL2State, Error, EventResponse, MockPlatform, Result};
+use egui::{Color32, ColorImage, Context, FullOutput, PlatformOutput};
+use sdl2::event::Event;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// How [`Harness::snapshot_with`] compares images.
+#[derive(Clone, Debug)]
+pub struct SnapshotOptions {
+    /// Where `<name>.png` is stored, and `<name>.new.png` and `<name>.diff.png` are written
+    /// when the comparison fails.
+    pub dir: PathBuf,
+    /// Largest difference of a color channel that still counts as equal.
+    pub threshold: u8,
+    /// Number of differing pixels that is still accepted.
+    pub max_failed_pixels: usize,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("tests/snapshots"),
+            threshold: 2,
+            max_failed_pixels: 0,
+        }
+    }
+}
+
+/// Drives an egui UI with synthetic SDL events and renders it offscreen.
+pub struct Harness<'a> {
+    pub ctx: Context,
+    pub state: EguiSDL2State,
+    pub platform: MockPlatform,
+    /// Builds the events for [`Harness::feed`].
+    pub input: InputSimulator,
+    renderer: SoftwareRenderer,
+    app: Box<dyn FnMut(&Context) + 'a>,
+    time: f64,
+    step_dt: f32,
+    output: FullOutput,
+}
+
+impl<'a> Harness<'a> {
+    /// A harness for a window of `width` x `height` points that shows `app`.
+    pub fn new(width: u32, height: u32, app: impl FnMut(&Context) + 'a) -> Self {
+        Self::with_pixels_per_point(width, height, 1.0, app)
+    }
+
+    /// Like [`Harness::new`], rendered with `pixels_per_point` pixels per point.
+    pub fn with_pixels_per_point(
+        width: u32,
+        height: u32,
+        pixels_per_point: f32,
+        app: impl FnMut(&Context) + 'a,
+    ) -> Self {
+        let width = (width as f32 * pixels_per_point).round() as u32;
+        let height = (height as f32 * pixels_per_point).round() as u32;
+        let mut state = EguiSDL2State::new(width, height, pixels_per_point);
+        let viewport_id = state.raw_input.viewport_id;
+        let info = state.raw_input.viewports.entry(viewport_id).or_default();
+        info.native_pixels_per_point = Some(pixels_per_point);
+        info.focused = Some(true);
+        state.raw_input.focused = true;
+        let ctx = Context::default();
+        #[cfg(feature = "accesskit")]
+        ctx.enable_accesskit();
+        let platform = MockPlatform::new(1, width, height);
+        Self {
+            ctx,
+            state,
+            input: InputSimulator::new(platform.window_id, pixels_per_point),
+            platform,
+            renderer: SoftwareRenderer::new(),
+            app: Box::new(app),
+            time: 0.0,
+            step_dt: 1.0 / 60.0,
+            output: FullOutput::default(),
+        }
+    }
+
+    /// The window id synthetic events have to carry.
+    pub fn window_id(&self) -> u32 {
+        self.platform.window_id
+    }
+
+    /// Feeds an SDL event to egui, as in a real event loop.
+    pub fn event(&mut self, event: &Event) -> EventResponse {
+        self.state.sdl2_input_to_egui(&self.platform, event)
+    }
+
+    /// Feeds a sequence of events, e.g. from [`Harness::input`].
+    pub fn feed(&mut self, events: impl IntoIterator<Item = Event>) {
+        crate::simulate::feed(&mut self.state, &self.platform, events);
+    }
+
+    /// Rectangle of the widget labelled `label` in the last frame, in points.
+    #[cfg(feature = "accesskit")]
+    pub fn find_by_label(&self, label: &str) -> Option<egui::Rect> {
+        crate::simulate::find_by_label(self.output(), label)
+    }
+
+    /// Runs and renders one frame. Time advances by a fixed step.
+    pub fn run(&mut self) {
+        self.state.update_time(Some(self.time), self.step_dt);
+        self.time += self.step_dt as f64;
+        let app = &mut self.app;
+        let output = self.ctx.run(self.state.raw_input.take(), |ctx| app(ctx));
+        self.state.update_input_capture(&self.ctx);
+        self.state.update_repaint_delay(&output);
+        self.state
+            .process_output(&self.platform, &output.platform_output);
+        // there is no window for the other viewport commands:
+        let commands = output.viewport_output.values().flat_map(|v| &v.commands);
+        for command in commands {
+            if let egui::ViewportCommand::Screenshot(user_data) = command {
+                self.state.screenshot_requests.push(user_data.clone());
+            }
+        }
+
+        let (width, height) = self.platform.window_size.get();
+        let clipped_primitives = self
+            .ctx
+            .tessellate(output.shapes.clone(), output.pixels_per_point);
+        self.renderer.update_textures(&output.textures_delta);
+        self.renderer.render(
+            [width as usize, height as usize],
+            output.pixels_per_point,
+            &clipped_primitives,
+        );
+        self.renderer.free_textures(&output.textures_delta);
+        if self.state.wants_screenshot() {
+            self.state.deliver_screenshot(self.image());
+        }
+        self.output = output;
+    }
+
+    /// Runs frames until egui stops asking for an immediate repaint, e.g. after animations
+    /// or layout passes, at most `max_steps`.
+    pub fn run_until_idle(&mut self, max_steps: usize) {
+        for _ in 0..max_steps {
+            self.run();
+            if self.state.repaint_delay() != Some(std::time::Duration::ZERO) {
+                break;
+            }
+        }
+    }
+
+    /// Output of the last frame.
+    pub fn output(&self) -> &PlatformOutput {
+        &self.output.platform_output
+    }
+
+    /// The last rendered frame.
+    pub fn image(&self) -> ColorImage {
+        ColorImage::new(self.renderer.size(), self.renderer.pixels().to_vec())
+    }
+
+    /// Compares the last frame to `tests/snapshots/<name>.png`, see [`Harness::snapshot_with`].
+    pub fn snapshot(&self, name: &str) -> Result<()> {
+        self.snapshot_with(name, &SnapshotOptions::default())
+    }
+
+    /// Compares the last frame to `<options.dir>/<name>.png`. On failure the frame is written
+    /// to `<name>.new.png` and the differing pixels are marked red in `<name>.diff.png`.
+    pub fn snapshot_with(&self, name: &str, options: &SnapshotOptions) -> Result<()> {
+        let image = self.image();
+        let expected_path = options.dir.join(format!("{name}.png"));
+        let new_path = options.dir.join(format!("{name}.new.png"));
+        let diff_path = options.dir.join(format!("{name}.diff.png"));
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            let _ = std::fs::remove_file(&new_path);
+            let _ = std::fs::remove_file(&diff_path);
+            return write_png(&expected_path, &image);
+        }
+        let expected = match read_png(&expected_path) {
+            Ok(expected) => expected,
+            Err(err) => {
+                write_png(&new_path, &image)?;
+                return Err(err);
+            }
+        };
+        if expected.size != image.size {
+            write_png(&new_path, &image)?;
+            return Err(Error::Snapshot(format!(
+                "{}: expected size {:?}, got {:?}",
+                expected_path.display(),
+                expected.size,
+                image.size
+            )));
+        }
+
+        let mut failed_pixels = 0;
+        let diff_pixels = image
+            .pixels
+            .iter()
+            .zip(&expected.pixels)
+            .map(|(actual, expected)| {
+                let differs = actual
+                    .to_array()
+                    .iter()
+                    .zip(expected.to_array())
+                    .any(|(a, e)| a.abs_diff(e) > options.threshold);
+                if differs {
+                    failed_pixels += 1;
+                    Color32::RED
+                } else {
+                    expected.gamma_multiply(0.25)
+                }
+            })
+            .collect();
+        if failed_pixels <= options.max_failed_pixels {
+            let _ = std::fs::remove_file(&new_path);
+            let _ = std::fs::remove_file(&diff_path);
+            return Ok(());
+        }
+        write_png(&new_path, &image)?;
+        write_png(&diff_path, &ColorImage::new(image.size, diff_pixels))?;
+        Err(Error::Snapshot(format!(
+            "{}: {failed_pixels} pixels differ, see {}",
+            expected_path.display(),
+            diff_path.display()
+        )))
+    }
+
+    /// Like [`Harness::snapshot`], but panics if the frame does not match.
+    #[track_caller]
+    pub fn assert_snapshot(&self, name: &str) {
+        if let Err(err) = self.snapshot(name) {
+            panic!("{err}");
+        }
+    }
+}
+
+fn write_png(path: &Path, image: &ColorImage) -> Result<()> {
+    let snapshot_error =
+        |e: &dyn std::fmt::Display| Error::Snapshot(format!("{}: {e}", path.display()));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| snapshot_error(&e))?;
+    }
+    let file = File::create(path).map_err(|e| snapshot_error(&e))?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        image.size[0] as u32,
+        image.size[1] as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let rgba: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|color| color.to_srgba_unmultiplied())
+        .collect();
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .map_err(|e| snapshot_error(&e))
+}
+
+fn read_png(path: &Path) -> Result<ColorImage> {
+    let snapshot_error =
+        |e: &dyn std::fmt::Display| Error::Snapshot(format!("{}: {e}", path.display()));
+    let file = File::open(path).map_err(|e| snapshot_error(&e))?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| snapshot_error(&e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| snapshot_error(&e))?;
+    let size = [info.width as usize, info.height as usize];
+    let bytes = &buffer[..info.buffer_size()];
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => bytes
+            .chunks_exact(4)
+            .map(|p| Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
+            .collect(),
+        png::ColorType::Rgb => bytes
+            .chunks_exact(3)
+            .map(|p| Color32::from_rgb(p[0], p[1], p[2]))
+            .collect(),
+        other => return Err(snapshot_error(&format!("unsupported color type {other:?}"))),
+    };
+    Ok(ColorImage::new(size, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ui(ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Snapshot");
+            let _ = ui.button("Button");
+        });
+    }
+
+    #[test]
+    fn matches_reference_image() {
+        let mut harness = Harness::new(120, 60, ui);
+        harness.run_until_idle(10);
+        harness.assert_snapshot("harness_basic");
+    }
+
+    #[test]
+    fn writes_diff_on_mismatch() {
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("egui-sdl2-snapshot-{}", std::process::id()));
+        let options = SnapshotOptions {
+            dir: dir.clone(),
+            ..Default::default()
+        };
+        write_png(
+            &dir.join("mismatch.png"),
+            &ColorImage::filled([120, 60], Color32::RED),
+        )
+        .unwrap();
+
+        let mut harness = Harness::new(120, 60, ui);
+        harness.run_until_idle(10);
+        assert!(harness.snapshot_with("mismatch", &options).is_err());
+        assert!(dir.join("mismatch.new.png").exists());
+        let diff = read_png(&dir.join("mismatch.diff.png")).unwrap();
+        assert_eq!(diff.size, [120, 60]);
+        assert!(diff.pixels.contains(&Color32::RED));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,437 @@
+use egui::{Key, Modifiers, MouseWheelUnit, PointerButton, Pos2};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+
+/// Everything besides the event itself that the translation of an SDL event depends on.
+///
+/// [`crate::EguiSDL2State`] keeps this up to date from the events it sees; other integrations
+/// can fill it in however they like.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TranslationContext {
+    /// Events for other windows are ignored.
+    pub window_id: u32,
+    pub pixels_per_point: f32,
+    /// Modifiers held down while the event happened. Key events carry their own.
+    pub modifiers: Modifiers,
+    /// Last known pointer position in points, used for mouse button events.
+    pub pointer_pos: Pos2,
+}
+
+impl Default for TranslationContext {
+    fn default() -> Self {
+        Self {
+            window_id: 0,
+            pixels_per_point: 1.0,
+            modifiers: Modifiers::default(),
+            pointer_pos: Pos2::ZERO,
+        }
+    }
+}
+
+pub fn translate_virtual_key_code(key: Keycode) -> Option<egui::Key> {
+    Some(match key {
+        Keycode::Left => Key::ArrowLeft,
+        Keycode::Up => Key::ArrowUp,
+        Keycode::Right => Key::ArrowRight,
+        Keycode::Down => Key::ArrowDown,
+
+        Keycode::Escape => Key::Escape,
+        Keycode::Tab => Key::Tab,
+        Keycode::Backspace => Key::Backspace,
+        Keycode::Space => Key::Space,
+        Keycode::Return => Key::Enter,
+
+        Keycode::Insert => Key::Insert,
+        Keycode::Home => Key::Home,
+        Keycode::Delete => Key::Delete,
+        Keycode::End => Key::End,
+        Keycode::PageDown => Key::PageDown,
+        Keycode::PageUp => Key::PageUp,
+
+        Keycode::Kp0 | Keycode::Num0 => Key::Num0,
+        Keycode::Kp1 | Keycode::Num1 => Key::Num1,
+        Keycode::Kp2 | Keycode::Num2 => Key::Num2,
+        Keycode::Kp3 | Keycode::Num3 => Key::Num3,
+        Keycode::Kp4 | Keycode::Num4 => Key::Num4,
+        Keycode::Kp5 | Keycode::Num5 => Key::Num5,
+        Keycode::Kp6 | Keycode::Num6 => Key::Num6,
+        Keycode::Kp7 | Keycode::Num7 => Key::Num7,
+        Keycode::Kp8 | Keycode::Num8 => Key::Num8,
+        Keycode::Kp9 | Keycode::Num9 => Key::Num9,
+
+        Keycode::A => Key::A,
+        Keycode::B => Key::B,
+        Keycode::C => Key::C,
+        Keycode::D => Key::D,
+        Keycode::E => Key::E,
+        Keycode::F => Key::F,
+        Keycode::G => Key::G,
+        Keycode::H => Key::H,
+        Keycode::I => Key::I,
+        Keycode::J => Key::J,
+        Keycode::K => Key::K,
+        Keycode::L => Key::L,
+        Keycode::M => Key::M,
+        Keycode::N => Key::N,
+        Keycode::O => Key::O,
+        Keycode::P => Key::P,
+        Keycode::Q => Key::Q,
+        Keycode::R => Key::R,
+        Keycode::S => Key::S,
+        Keycode::T => Key::T,
+        Keycode::U => Key::U,
+        Keycode::V => Key::V,
+        Keycode::W => Key::W,
+        Keycode::X => Key::X,
+        Keycode::Y => Key::Y,
+        Keycode::Z => Key::Z,
+
+        _ => {
+            return None;
+        }
+    })
+}
+
+pub fn translate_mouse_button(btn: MouseButton) -> Option<PointerButton> {
+    match btn {
+        MouseButton::Left => Some(egui::PointerButton::Primary),
+        MouseButton::Middle => Some(egui::PointerButton::Middle),
+        MouseButton::Right => Some(egui::PointerButton::Secondary),
+        _ => None,
+    }
+}
+
+pub fn translate_modifiers(keymod: Mod) -> Modifiers {
+    modifiers_for_platform(keymod, cfg!(target_os = "macos"))
+}
+
+/// Like egui's own integrations, `command` is Cmd on macOS and Ctrl everywhere else, and
+/// `mac_cmd` is only ever set on macOS.
+fn modifiers_for_platform(keymod: Mod, is_mac: bool) -> Modifiers {
+    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+    let gui = keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD);
+    Modifiers {
+        alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        ctrl,
+        shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+        mac_cmd: is_mac && gui,
+        command: if is_mac { gui } else { ctrl },
+    }
+}
+
+/// Maps an SDL event to the egui events it stands for, without touching SDL.
+///
+/// Window state changes (resizes, close requests) and the clipboard are left to the caller;
+/// for paste, look for a pressed `Key::V` with `modifiers.command` in the result.
+pub fn translate_event(event: &Event, ctx: &TranslationContext) -> Vec<egui::Event> {
+    if event.get_window_id() != Some(ctx.window_id) {
+        return Vec::new();
+    }
+    let pixels_per_point = ctx.pixels_per_point;
+    let mut events = Vec::new();
+    match event {
+        Event::MouseButtonDown { mouse_btn, .. } | Event::MouseButtonUp { mouse_btn, .. } => {
+            if let Some(button) = translate_mouse_button(*mouse_btn) {
+                events.push(egui::Event::PointerButton {
+                    pos: ctx.pointer_pos,
+                    button,
+                    pressed: matches!(event, Event::MouseButtonDown { .. }),
+                    modifiers: ctx.modifiers,
+                });
+            }
+        }
+
+        Event::MouseMotion {
+            x, y, xrel, yrel, ..
+        } => {
+            events.push(egui::Event::PointerMoved(egui::pos2(
+                *x as f32 / pixels_per_point,
+                *y as f32 / pixels_per_point,
+            )));
+            // raw deltas keep flowing in relative mouse mode where the position is frozen:
+            events.push(egui::Event::MouseMoved(egui::vec2(
+                *xrel as f32 / pixels_per_point,
+                *yrel as f32 / pixels_per_point,
+            )));
+        }
+
+        Event::KeyDown {
+            keycode: Some(keycode),
+            keymod,
+            ..
+        }
+        | Event::KeyUp {
+            keycode: Some(keycode),
+            keymod,
+            ..
+        } => {
+            let Some(key) = translate_virtual_key_code(*keycode) else {
+                return events;
+            };
+            let pressed = matches!(event, Event::KeyDown { .. });
+            let modifiers = translate_modifiers(*keymod);
+            events.push(egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers,
+            });
+
+            if pressed && modifiers.command && key == Key::C {
+                events.push(egui::Event::Copy);
+            } else if pressed && modifiers.command && key == Key::X {
+                events.push(egui::Event::Cut);
+            }
+        }
+
+        Event::TextInput { text, .. } => {
+            events.push(egui::Event::Text(text.clone()));
+        }
+
+        Event::MouseWheel { x, y, .. } => {
+            let delta = egui::vec2(*x as f32 * 8.0, *y as f32 * 8.0);
+            // zoom:
+            if ctx.modifiers.ctrl {
+                let zoom_delta = (delta.y / 125.0).exp();
+                events.push(egui::Event::Zoom(zoom_delta));
+            }
+            // horizontal scroll:
+            else if ctx.modifiers.shift {
+                events.push(egui::Event::MouseWheel {
+                    unit: MouseWheelUnit::Point,
+                    delta: egui::vec2(delta.x + delta.y, 0.0),
+                    modifiers: Default::default(),
+                });
+                // regular scroll:
+            } else {
+                events.push(egui::Event::MouseWheel {
+                    unit: MouseWheelUnit::Point,
+                    delta: egui::vec2(delta.x, delta.y),
+                    modifiers: Default::default(),
+                });
+            }
+        }
+        _ => {}
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::mouse::{MouseState, MouseWheelDirection};
+
+    const WINDOW: u32 = 1;
+
+    fn ctx() -> TranslationContext {
+        TranslationContext {
+            window_id: WINDOW,
+            pixels_per_point: 2.0,
+            modifiers: Modifiers::default(),
+            pointer_pos: egui::pos2(5.0, 6.0),
+        }
+    }
+
+    fn key_down(keycode: Keycode, keymod: Mod) -> Event {
+        Event::KeyDown {
+            timestamp: 0,
+            window_id: WINDOW,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod,
+            repeat: false,
+        }
+    }
+
+    fn button(mouse_btn: MouseButton, down: bool) -> Event {
+        if down {
+            Event::MouseButtonDown {
+                timestamp: 0,
+                window_id: WINDOW,
+                which: 0,
+                mouse_btn,
+                clicks: 1,
+                x: 10,
+                y: 12,
+            }
+        } else {
+            Event::MouseButtonUp {
+                timestamp: 0,
+                window_id: WINDOW,
+                which: 0,
+                mouse_btn,
+                clicks: 1,
+                x: 10,
+                y: 12,
+            }
+        }
+    }
+
+    fn wheel(x: i32, y: i32) -> Event {
+        Event::MouseWheel {
+            timestamp: 0,
+            window_id: WINDOW,
+            which: 0,
+            x,
+            y,
+            direction: MouseWheelDirection::Normal,
+            precise_x: x as f32,
+            precise_y: y as f32,
+            mouse_x: 0,
+            mouse_y: 0,
+        }
+    }
+
+    fn key(key: Key, modifiers: Modifiers) -> egui::Event {
+        egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn translate_event_table() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            command: true,
+            ..Default::default()
+        };
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let (command_mod, command) = if cfg!(target_os = "macos") {
+            (
+                Mod::LGUIMOD,
+                Modifiers {
+                    mac_cmd: true,
+                    command: true,
+                    ..Default::default()
+                },
+            )
+        } else {
+            (Mod::LCTRLMOD, ctrl)
+        };
+        let cases: Vec<(&str, Event, TranslationContext, Vec<egui::Event>)> = vec![
+            (
+                "motion is scaled to points and reports the raw delta",
+                Event::MouseMotion {
+                    timestamp: 0,
+                    window_id: WINDOW,
+                    which: 0,
+                    mousestate: MouseState::from_sdl_state(0),
+                    x: 20,
+                    y: 40,
+                    xrel: 4,
+                    yrel: -2,
+                },
+                ctx(),
+                vec![
+                    egui::Event::PointerMoved(egui::pos2(10.0, 20.0)),
+                    egui::Event::MouseMoved(egui::vec2(2.0, -1.0)),
+                ],
+            ),
+            (
+                "button down at the last pointer position",
+                button(MouseButton::Left, true),
+                ctx(),
+                vec![egui::Event::PointerButton {
+                    pos: egui::pos2(5.0, 6.0),
+                    button: PointerButton::Primary,
+                    pressed: true,
+                    modifiers: Modifiers::default(),
+                }],
+            ),
+            (
+                "button up",
+                button(MouseButton::Right, false),
+                ctx(),
+                vec![egui::Event::PointerButton {
+                    pos: egui::pos2(5.0, 6.0),
+                    button: PointerButton::Secondary,
+                    pressed: false,
+                    modifiers: Modifiers::default(),
+                }],
+            ),
+            (
+                "key with modifiers",
+                key_down(Keycode::A, Mod::LSHIFTMOD),
+                ctx(),
+                vec![key(Key::A, shift)],
+            ),
+            (
+                "command+c copies",
+                key_down(Keycode::C, command_mod),
+                ctx(),
+                vec![key(Key::C, command), egui::Event::Copy],
+            ),
+            (
+                "command+x cuts",
+                key_down(Keycode::X, command_mod),
+                ctx(),
+                vec![key(Key::X, command), egui::Event::Cut],
+            ),
+            (
+                "ctrl+wheel zooms",
+                wheel(0, 1),
+                TranslationContext {
+                    modifiers: ctrl,
+                    ..ctx()
+                },
+                vec![egui::Event::Zoom((8.0f32 / 125.0).exp())],
+            ),
+            (
+                "shift+wheel scrolls horizontally",
+                wheel(0, 1),
+                TranslationContext {
+                    modifiers: shift,
+                    ..ctx()
+                },
+                vec![egui::Event::MouseWheel {
+                    unit: MouseWheelUnit::Point,
+                    delta: egui::vec2(8.0, 0.0),
+                    modifiers: Modifiers::default(),
+                }],
+            ),
+            (
+                "events for another window are ignored",
+                key_down(Keycode::A, Mod::NOMOD),
+                TranslationContext {
+                    window_id: WINDOW + 1,
+                    ..ctx()
+                },
+                vec![],
+            ),
+        ];
+        for (name, event, ctx, expected) in cases {
+            assert_eq!(translate_event(&event, &ctx), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn command_is_cmd_on_mac_and_ctrl_elsewhere() {
+        let cases = [
+            (Mod::RCTRLMOD, false, Modifiers::CTRL | Modifiers::COMMAND),
+            (Mod::LGUIMOD, false, Modifiers::NONE),
+            (Mod::RCTRLMOD, true, Modifiers::CTRL),
+            (Mod::RGUIMOD, true, Modifiers::MAC_CMD | Modifiers::COMMAND),
+            (
+                Mod::LALTMOD | Mod::RSHIFTMOD,
+                true,
+                Modifiers::ALT | Modifiers::SHIFT,
+            ),
+        ];
+        for (keymod, is_mac, expected) in cases {
+            assert_eq!(
+                modifiers_for_platform(keymod, is_mac),
+                expected,
+                "{keymod:?} on mac: {is_mac}"
+            );
+        }
+    }
+}
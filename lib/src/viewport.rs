@@ -0,0 +1,655 @@
+use crate::{EguiSDL2State, Error, EventResponse, Platform, Result};
+use egui::{
+    Context, DeferredViewportUiCallback, IconData, ImmediateViewport, RawInput, Rect,
+    ViewportBuilder, ViewportClass, ViewportCommand, ViewportId, ViewportIdMap, ViewportIdPair,
+    ViewportInfo, WindowLevel,
+};
+use sdl2::event::Event;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use sdl2::sys::SDL_WindowFlags;
+use sdl2::video::{FlashOperation, FullscreenType, Window, WindowBuilder, WindowPos};
+use sdl2::VideoSubsystem;
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+use std::sync::Arc;
+
+const DEFAULT_TITLE: &str = "egui";
+const DEFAULT_INNER_SIZE: egui::Vec2 = egui::vec2(800.0, 600.0);
+
+/// Translates everything of a [`ViewportBuilder`] that can be decided at window creation time
+/// into a [`WindowBuilder`]. Sizes and positions are given in points and converted with
+/// `pixels_per_point`.
+///
+/// Settings SDL can only change on an existing window (minimum/maximum size, icon) are
+/// applied by [`apply_viewport_builder`]. `transparent` and `mouse_passthrough` have no SDL2
+/// equivalent and are ignored.
+pub fn window_builder(
+    video: &VideoSubsystem,
+    builder: &ViewportBuilder,
+    pixels_per_point: f32,
+) -> WindowBuilder {
+    let title = builder.title.as_deref().unwrap_or(DEFAULT_TITLE);
+    let size = builder.inner_size.unwrap_or(DEFAULT_INNER_SIZE) * pixels_per_point;
+    let mut window_builder = video.window(title, size.x.round() as u32, size.y.round() as u32);
+
+    match builder.position {
+        Some(pos) => {
+            let pos = pos * pixels_per_point;
+            window_builder.position(pos.x.round() as i32, pos.y.round() as i32);
+        }
+        None => {
+            window_builder.position_centered();
+        }
+    }
+    if builder.resizable.unwrap_or(true) {
+        window_builder.resizable();
+    }
+    if builder.decorations == Some(false) {
+        window_builder.borderless();
+    }
+    if builder.fullscreen == Some(true) {
+        window_builder.fullscreen_desktop();
+    }
+    if builder.maximized == Some(true) {
+        window_builder.maximized();
+    }
+    if builder.visible == Some(false) {
+        window_builder.hidden();
+    }
+    if builder.window_level == Some(WindowLevel::AlwaysOnTop) {
+        window_builder.always_on_top();
+    }
+    if builder.taskbar == Some(false) {
+        let flags = window_builder.window_flags()
+            | sdl2::sys::SDL_WindowFlags::SDL_WINDOW_SKIP_TASKBAR as u32;
+        window_builder.set_window_flags(flags);
+    }
+    window_builder
+}
+
+/// Applies the parts of a [`ViewportBuilder`] that SDL only supports on existing windows.
+pub fn apply_viewport_builder(
+    window: &mut Window,
+    builder: &ViewportBuilder,
+    pixels_per_point: f32,
+) -> Result<()> {
+    if let Some(size) = builder.min_inner_size {
+        let size = size * pixels_per_point;
+        window
+            .set_minimum_size(size.x.round() as u32, size.y.round() as u32)
+            .map_err(|e| Error::Window(e.to_string()))?;
+    }
+    if let Some(size) = builder.max_inner_size {
+        let size = size * pixels_per_point;
+        window
+            .set_maximum_size(size.x.round() as u32, size.y.round() as u32)
+            .map_err(|e| Error::Window(e.to_string()))?;
+    }
+    if let Some(icon) = &builder.icon {
+        set_window_icon(window, icon)?;
+    }
+    Ok(())
+}
+
+/// Sets the window icon from egui's unmultiplied RGBA [`IconData`].
+pub fn set_window_icon(window: &mut Window, icon: &IconData) -> Result<()> {
+    let mut rgba = icon.rgba.clone();
+    let surface = Surface::from_data(
+        &mut rgba,
+        icon.width,
+        icon.height,
+        icon.width * 4,
+        PixelFormatEnum::RGBA32,
+    )
+    .map_err(Error::Window)?;
+    window.set_icon(surface);
+    Ok(())
+}
+
+/// Creates a window configured from a [`ViewportBuilder`] together with a matching
+/// [`EguiSDL2State`], with one SDL window coordinate per point.
+pub fn create_window(
+    video: &VideoSubsystem,
+    builder: &ViewportBuilder,
+) -> Result<(Window, EguiSDL2State)> {
+    create_window_with(video, builder, 1.0, |_| {})
+}
+
+/// Like [`create_window`], but scales the UI by `pixels_per_point` SDL window coordinates per
+/// point and lets `configure` adjust the [`WindowBuilder`] before the window is created, e.g.
+/// to request an OpenGL window.
+///
+/// SDL has no reliable UI scale factor: display DPIs are physical, and on macOS window
+/// coordinates already are points. So the scale is left to the application, e.g. from a
+/// setting or an environment variable.
+pub fn create_window_with(
+    video: &VideoSubsystem,
+    builder: &ViewportBuilder,
+    pixels_per_point: f32,
+    configure: impl FnOnce(&mut WindowBuilder),
+) -> Result<(Window, EguiSDL2State)> {
+    let mut window_builder = window_builder(video, builder, pixels_per_point);
+    configure(&mut window_builder);
+    let mut window = window_builder
+        .build()
+        .map_err(|e| Error::Window(e.to_string()))?;
+
+    apply_viewport_builder(&mut window, builder, pixels_per_point)?;
+
+    let (width, height) = window.size();
+    let mut state = EguiSDL2State::new(width, height, pixels_per_point);
+    let viewport_id = state.raw_input.viewport_id;
+    let info = state.raw_input.viewports.entry(viewport_id).or_default();
+    info.title = Some(window.title().to_owned());
+    info.native_pixels_per_point = Some(pixels_per_point);
+    Ok((window, state))
+}
+
+/// Applies a window-related [`ViewportCommand`] to `window`. Commands without an SDL2
+/// equivalent are ignored. Returns an error if the window rejected it.
+pub fn apply_viewport_command(
+    window: &mut Window,
+    command: &ViewportCommand,
+    pixels_per_point: f32,
+) -> Result<()> {
+    let to_pixels = |size: egui::Vec2| {
+        let size = size * pixels_per_point;
+        (
+            size.x.round().max(0.0) as u32,
+            size.y.round().max(0.0) as u32,
+        )
+    };
+    let sdl = window.subsystem().sdl();
+    match command {
+        ViewportCommand::Title(title) => window
+            .set_title(title)
+            .map_err(|e| Error::Window(e.to_string()))?,
+        ViewportCommand::Visible(true) => window.show(),
+        ViewportCommand::Visible(false) => window.hide(),
+        ViewportCommand::OuterPosition(pos) => {
+            let pos = *pos * pixels_per_point;
+            window.set_position(
+                WindowPos::Positioned(pos.x.round() as i32),
+                WindowPos::Positioned(pos.y.round() as i32),
+            );
+        }
+        ViewportCommand::InnerSize(size) => {
+            let (width, height) = to_pixels(*size);
+            window
+                .set_size(width.max(1), height.max(1))
+                .map_err(|e| Error::Window(e.to_string()))?;
+        }
+        ViewportCommand::MinInnerSize(size) => {
+            let (width, height) = to_pixels(*size);
+            window
+                .set_minimum_size(width, height)
+                .map_err(|e| Error::Window(e.to_string()))?;
+        }
+        ViewportCommand::MaxInnerSize(size) => {
+            let (width, height) = to_pixels(*size);
+            window
+                .set_maximum_size(width, height)
+                .map_err(|e| Error::Window(e.to_string()))?;
+        }
+        ViewportCommand::Resizable(resizable) => window.set_resizable(*resizable),
+        ViewportCommand::Minimized(true) => window.minimize(),
+        ViewportCommand::Maximized(true) => window.maximize(),
+        ViewportCommand::Minimized(false) | ViewportCommand::Maximized(false) => window.restore(),
+        ViewportCommand::Fullscreen(fullscreen) => window
+            .set_fullscreen(if *fullscreen {
+                FullscreenType::Desktop
+            } else {
+                FullscreenType::Off
+            })
+            .map_err(Error::Window)?,
+        ViewportCommand::Decorations(decorations) => window.set_bordered(*decorations),
+        ViewportCommand::WindowLevel(level) => {
+            window.set_always_on_top(*level == WindowLevel::AlwaysOnTop)
+        }
+        ViewportCommand::Icon(Some(icon)) => set_window_icon(window, icon)?,
+        ViewportCommand::Focus => window.raise(),
+        ViewportCommand::RequestUserAttention(attention) => window
+            .flash(match attention {
+                egui::UserAttentionType::Critical => FlashOperation::UntilFocused,
+                egui::UserAttentionType::Informational => FlashOperation::Briefly,
+                egui::UserAttentionType::Reset => FlashOperation::Cancel,
+            })
+            .map_err(Error::Window)?,
+        ViewportCommand::CursorPosition(pos) => {
+            let pos = *pos * pixels_per_point;
+            sdl.mouse()
+                .warp_mouse_in_window(window, pos.x.round() as i32, pos.y.round() as i32);
+        }
+        ViewportCommand::CursorGrab(grab) => {
+            sdl.mouse()
+                .set_relative_mouse_mode(*grab == egui::viewport::CursorGrab::Locked);
+            window.set_mouse_grab(*grab == egui::viewport::CursorGrab::Confined);
+        }
+        ViewportCommand::CursorVisible(visible) => sdl.mouse().show_cursor(*visible),
+        ViewportCommand::IMEAllowed(allowed) => Platform::set_text_input(window, *allowed),
+        ViewportCommand::IMERect(rect) => {
+            let rect = *rect * pixels_per_point;
+            window.set_text_input_rect(sdl2::rect::Rect::new(
+                rect.min.x.round() as i32,
+                rect.min.y.round() as i32,
+                rect.width().round().max(1.0) as u32,
+                rect.height().round().max(1.0) as u32,
+            ));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A native SDL window showing one egui viewport.
+pub struct Viewport {
+    pub id: ViewportId,
+    pub parent: ViewportId,
+    pub class: ViewportClass,
+    pub window: Window,
+    pub state: EguiSDL2State,
+    builder: ViewportBuilder,
+    viewport_ui_cb: Option<Arc<DeferredViewportUiCallback>>,
+    /// Commands egui sent during this frame, applied once all passes have run.
+    commands: Vec<ViewportCommand>,
+}
+
+impl Viewport {
+    fn create(
+        video: &VideoSubsystem,
+        pixels_per_point: f32,
+        ids: ViewportIdPair,
+        class: ViewportClass,
+        builder: ViewportBuilder,
+        viewport_ui_cb: Option<Arc<DeferredViewportUiCallback>>,
+    ) -> Result<Self> {
+        let (window, mut state) = create_window_with(video, &builder, pixels_per_point, |_| {})?;
+        state.raw_input.viewport_id = ids.this;
+        let root_info = state.raw_input.viewports.remove(&ViewportId::ROOT);
+        state
+            .raw_input
+            .viewports
+            .insert(ids.this, root_info.unwrap_or_default());
+        Ok(Self {
+            id: ids.this,
+            parent: ids.parent,
+            class,
+            window,
+            state,
+            builder,
+            viewport_ui_cb,
+            commands: Vec::new(),
+        })
+    }
+
+    /// Describes the current window state to egui. Pending events such as close requests are
+    /// handed over with `take_events`, and left for the viewport's own pass otherwise.
+    fn info(&mut self, take_events: bool) -> ViewportInfo {
+        let pixels_per_point = self.state.dpi_scaling;
+        let info = self.state.raw_input.viewports.get_mut(&self.id);
+        let mut info = match info {
+            Some(info) if take_events => info.take(),
+            Some(info) => info.clone(),
+            None => ViewportInfo::default(),
+        };
+
+        let (x, y) = self.window.position();
+        let (width, height) = self.window.size();
+        let inner_rect = Rect::from_min_size(
+            egui::pos2(x as f32, y as f32) / pixels_per_point,
+            egui::vec2(width as f32, height as f32) / pixels_per_point,
+        );
+        let flags = self.window.window_flags();
+        let has_flag = |flag: SDL_WindowFlags| flags & flag as u32 != 0;
+
+        info.parent = Some(self.parent);
+        info.title = Some(self.window.title().to_owned());
+        info.native_pixels_per_point = Some(pixels_per_point);
+        info.inner_rect = Some(inner_rect);
+        info.outer_rect = Some(inner_rect);
+        info.minimized = Some(has_flag(SDL_WindowFlags::SDL_WINDOW_MINIMIZED));
+        info.maximized = Some(has_flag(SDL_WindowFlags::SDL_WINDOW_MAXIMIZED));
+        info.fullscreen = Some(has_flag(SDL_WindowFlags::SDL_WINDOW_FULLSCREEN));
+        info.focused = Some(has_flag(SDL_WindowFlags::SDL_WINDOW_INPUT_FOCUS));
+        info
+    }
+}
+
+/// Output of one egui pass, to be painted into the window of `viewport_id`.
+pub struct ViewportFrame {
+    pub viewport_id: ViewportId,
+    pub full_output: egui::FullOutput,
+}
+
+struct Viewports {
+    video: VideoSubsystem,
+    /// Scale of the root viewport, used for all windows.
+    pixels_per_point: f32,
+    viewports: ViewportIdMap<Viewport>,
+    frames: Vec<ViewportFrame>,
+}
+
+impl Viewports {
+    /// `None` if there is no window for `viewport_id` (anymore).
+    fn take_raw_input(&mut self, viewport_id: ViewportId) -> Option<RawInput> {
+        if !self.viewports.contains_key(&viewport_id) {
+            warn!("No window for viewport {viewport_id:?}");
+            return None;
+        }
+        let mut infos = ViewportIdMap::default();
+        for viewport in self.viewports.values_mut() {
+            infos.insert(viewport.id, viewport.info(viewport.id == viewport_id));
+        }
+        let viewport = self.viewports.get_mut(&viewport_id)?;
+        let mut raw_input = viewport.state.raw_input.take();
+        raw_input.viewport_id = viewport_id;
+        raw_input.viewports = infos;
+        raw_input.focused = raw_input.viewports[&viewport_id].focused == Some(true);
+        Some(raw_input)
+    }
+
+    /// Remembers which input egui wants in `viewport_id`. Has to be called during the pass,
+    /// afterwards `ctx` answers for the root viewport.
+    fn update_input_capture(&mut self, viewport_id: ViewportId, ctx: &Context) {
+        if let Some(viewport) = self.viewports.get_mut(&viewport_id) {
+            viewport.state.update_input_capture(ctx);
+        }
+    }
+
+    /// Handles the output of a pass of `viewport_id`. Outputs of top level (not immediate)
+    /// passes list every live viewport, so windows are created, updated and destroyed to
+    /// match them. Their viewport commands are queued for [`Viewports::finish_frame`].
+    fn handle_output(
+        &mut self,
+        viewport_id: ViewportId,
+        full_output: egui::FullOutput,
+        is_top_level: bool,
+    ) {
+        if let Some(viewport) = self.viewports.get_mut(&viewport_id) {
+            viewport
+                .state
+                .process_output(&viewport.window, &full_output.platform_output);
+        }
+
+        if is_top_level {
+            let viewport_output = &full_output.viewport_output;
+            self.viewports
+                .retain(|id, _| *id == ViewportId::ROOT || viewport_output.contains_key(id));
+            for (id, output) in viewport_output {
+                self.sync_viewport(*id, output.clone());
+            }
+        }
+
+        self.frames.push(ViewportFrame {
+            viewport_id,
+            full_output,
+        });
+    }
+
+    fn sync_viewport(&mut self, id: ViewportId, output: egui::ViewportOutput) {
+        let egui::ViewportOutput {
+            parent,
+            class,
+            builder,
+            viewport_ui_cb,
+            mut commands,
+            ..
+        } = output;
+        let ids = ViewportIdPair::from_self_and_parent(id, parent);
+
+        let recreate = match self.viewports.get_mut(&id) {
+            Some(viewport) => {
+                let (patch_commands, recreate) = viewport.builder.patch(builder.clone());
+                commands.splice(0..0, patch_commands);
+                viewport.class = class;
+                viewport.parent = parent;
+                viewport.viewport_ui_cb = viewport_ui_cb.clone();
+                // the root window belongs to the host application and is never recreated:
+                recreate && id != ViewportId::ROOT
+            }
+            None => true,
+        };
+        if recreate {
+            let created = Viewport::create(
+                &self.video,
+                self.pixels_per_point,
+                ids,
+                class,
+                builder,
+                viewport_ui_cb,
+            );
+            match created {
+                Ok(viewport) => {
+                    self.viewports.insert(id, viewport);
+                }
+                Err(e) => {
+                    warn!("Unable to create a window for viewport {id:?}: {e}");
+                    return;
+                }
+            }
+        }
+
+        if let Some(viewport) = self.viewports.get_mut(&id) {
+            viewport.commands.append(&mut commands);
+        }
+    }
+
+    /// Applies the commands of all passes of the frame, once per viewport, and closes the
+    /// child windows that egui did not keep open. Returns the frames of the passes.
+    fn finish_frame(&mut self) -> Vec<ViewportFrame> {
+        let mut closed = Vec::new();
+        for viewport in self.viewports.values_mut() {
+            let commands = std::mem::take(&mut viewport.commands);
+            viewport
+                .state
+                .process_viewport_commands(&mut viewport.window, &commands);
+            if viewport.id != ViewportId::ROOT && viewport.state.should_close() {
+                closed.push(viewport.id);
+            }
+        }
+        for id in closed {
+            self.viewports.remove(&id);
+        }
+
+        let frames = std::mem::take(&mut self.frames);
+        let repaint_delay = frames
+            .iter()
+            .flat_map(|frame| frame.full_output.viewport_output.values())
+            .map(|output| output.repaint_delay)
+            .min();
+        if let (Some(root), Some(delay)) =
+            (self.viewports.get_mut(&ViewportId::ROOT), repaint_delay)
+        {
+            root.state.set_repaint_delay(delay);
+        }
+        frames
+    }
+}
+
+/// Runs egui with one SDL window per egui viewport, so `Context::show_viewport_immediate` and
+/// `Context::show_viewport_deferred` open real OS windows.
+///
+/// Feed every SDL event to [`ViewportManager::handle_event`] and call [`ViewportManager::run`]
+/// once per frame instead of `Context::run`. Platform output and viewport commands are applied
+/// by the manager; only painting is left to the caller.
+///
+/// A child window whose close request egui does not cancel with `ViewportCommand::CancelClose`
+/// is closed at the end of the frame. Stop showing the viewport once
+/// `ctx.input(|i| i.viewport().close_requested())` is seen, or egui opens it again. The manager must not be accessed from
+/// inside the UI code, as immediate viewports are rendered while it runs.
+pub struct ViewportManager {
+    viewports: Rc<RefCell<Viewports>>,
+}
+
+impl ViewportManager {
+    /// Takes over the root window and its state, and registers the immediate viewport renderer
+    /// for this thread.
+    pub fn new(ctx: &Context, root_window: Window, root_state: EguiSDL2State) -> Self {
+        let video = root_window.subsystem().clone();
+        let pixels_per_point = root_state.dpi_scaling;
+        let mut viewports = ViewportIdMap::default();
+        viewports.insert(
+            ViewportId::ROOT,
+            Viewport {
+                id: ViewportId::ROOT,
+                parent: ViewportId::ROOT,
+                class: ViewportClass::Root,
+                window: root_window,
+                state: root_state,
+                builder: ViewportBuilder::default(),
+                viewport_ui_cb: None,
+                commands: Vec::new(),
+            },
+        );
+        let viewports = Rc::new(RefCell::new(Viewports {
+            video,
+            pixels_per_point,
+            viewports,
+            frames: Vec::new(),
+        }));
+
+        ctx.set_embed_viewports(false);
+        let weak = Rc::downgrade(&viewports);
+        Context::set_immediate_viewport_renderer(move |ctx, mut immediate| match weak.upgrade() {
+            Some(viewports) => render_immediate_viewport(&viewports, ctx, immediate),
+            // the manager is gone, fall back to drawing the viewport inline:
+            None => (immediate.viewport_ui_cb)(ctx),
+        });
+
+        Self { viewports }
+    }
+
+    /// Routes an SDL event to the viewport whose window it belongs to. Events without a
+    /// window, like `Quit` or controller events, go to the root viewport.
+    pub fn handle_event(&self, event: &Event) -> EventResponse {
+        let mut viewports = self.viewports.borrow_mut();
+        let viewport = match event.get_window_id() {
+            Some(window_id) => viewports
+                .viewports
+                .values_mut()
+                .find(|viewport| viewport.window.id() == window_id),
+            None => viewports.viewports.get_mut(&ViewportId::ROOT),
+        };
+        match viewport {
+            Some(viewport) => viewport.state.sdl2_input_to_egui(&viewport.window, event),
+            None => EventResponse::default(),
+        }
+    }
+
+    /// Runs the root viewport with `run_ui`, then every deferred viewport with its own callback.
+    ///
+    /// Returns one [`ViewportFrame`] per pass, in the order they ran, for painting.
+    pub fn run(&self, ctx: &Context, run_ui: impl FnMut(&Context)) -> Vec<ViewportFrame> {
+        let raw_input = self.viewports.borrow_mut().take_raw_input(ViewportId::ROOT);
+        let Some(raw_input) = raw_input else {
+            return Vec::new();
+        };
+        let full_output = ctx.run(raw_input, run_ui);
+        let mut viewports = self.viewports.borrow_mut();
+        viewports.update_input_capture(ViewportId::ROOT, ctx);
+        viewports.handle_output(ViewportId::ROOT, full_output, true);
+        drop(viewports);
+
+        let deferred: Vec<_> = self
+            .viewports
+            .borrow()
+            .viewports
+            .values()
+            .filter_map(|viewport| Some((viewport.id, viewport.viewport_ui_cb.clone()?)))
+            .collect();
+        for (id, viewport_ui_cb) in deferred {
+            // a previous pass may have closed it:
+            if !self.viewports.borrow().viewports.contains_key(&id) {
+                continue;
+            }
+            let Some(raw_input) = self.viewports.borrow_mut().take_raw_input(id) else {
+                continue;
+            };
+            let full_output = ctx.run(raw_input, |ctx| {
+                viewport_ui_cb(ctx);
+                self.viewports.borrow_mut().update_input_capture(id, ctx);
+            });
+            self.viewports
+                .borrow_mut()
+                .handle_output(id, full_output, true);
+        }
+
+        self.viewports.borrow_mut().finish_frame()
+    }
+
+    /// Ids of all viewports that currently have a window.
+    pub fn viewport_ids(&self) -> Vec<ViewportId> {
+        self.viewports.borrow().viewports.keys().copied().collect()
+    }
+
+    /// The viewport with `id`, e.g. to paint a [`ViewportFrame`] into its window.
+    ///
+    /// Windows can be recreated when egui changes settings SDL can't change on the fly, so
+    /// compare `window.id()` when caching per-window resources.
+    pub fn viewport_mut(&self, id: ViewportId) -> Option<RefMut<'_, Viewport>> {
+        RefMut::filter_map(self.viewports.borrow_mut(), |viewports| {
+            viewports.viewports.get_mut(&id)
+        })
+        .ok()
+    }
+
+    /// See [`EguiSDL2State::should_close`], for the root viewport.
+    pub fn should_close(&self) -> bool {
+        self.viewport_mut(ViewportId::ROOT)
+            .is_none_or(|root| root.state.should_close())
+    }
+
+    /// [`EguiSDL2State::wait_for_event`] for the root viewport, which receives the repaint
+    /// requests of all viewports.
+    pub fn wait_for_event(&self, event_pump: &mut sdl2::EventPump) -> Option<Event> {
+        let root = self.viewport_mut(ViewportId::ROOT)?;
+        root.state.wait_for_event(&root.window, event_pump)
+    }
+}
+
+fn render_immediate_viewport(
+    viewports: &RefCell<Viewports>,
+    ctx: &Context,
+    immediate: ImmediateViewport<'_>,
+) {
+    let ImmediateViewport {
+        ids,
+        builder,
+        mut viewport_ui_cb,
+    } = immediate;
+
+    let raw_input = {
+        let mut viewports = viewports.borrow_mut();
+        if !viewports.viewports.contains_key(&ids.this) {
+            match Viewport::create(
+                &viewports.video,
+                viewports.pixels_per_point,
+                ids,
+                ViewportClass::Immediate,
+                builder,
+                None,
+            ) {
+                Ok(viewport) => {
+                    viewports.viewports.insert(ids.this, viewport);
+                }
+                Err(e) => {
+                    warn!("Unable to create a window for viewport {:?}: {e}", ids.this);
+                    return;
+                }
+            }
+        }
+        viewports.take_raw_input(ids.this)
+    };
+    let Some(raw_input) = raw_input else {
+        return;
+    };
+
+    let full_output = ctx.run(raw_input, |ctx| {
+        viewport_ui_cb(ctx);
+        viewports.borrow_mut().update_input_capture(ids.this, ctx);
+    });
+    viewports
+        .borrow_mut()
+        .handle_output(ids.this, full_output, false);
+}
@@ -0,0 +1,36 @@
+//! Checks [`FrameClock`] against SDL's real timer and event queue.
+
+use egui_sdl2_event::{EguiSDL2State, FrameClock, MockPlatform};
+use std::time::{Duration, Instant};
+
+#[test]
+fn waiting_is_not_frame_time() {
+    let sdl = sdl2::init().unwrap();
+    let mut event_pump = sdl.event_pump().unwrap();
+    let platform = MockPlatform::default();
+    let mut state = EguiSDL2State::new(800, 600, 1.0);
+    let mut clock = FrameClock::new(sdl.timer().unwrap());
+
+    // an idle UI that wants the next frame in 200 ms, once egui's first passes are done:
+    let ctx = egui::Context::default();
+    for _ in 0..3 {
+        let full_output = ctx.run(state.raw_input.take(), |ctx| {
+            ctx.request_repaint_after(Duration::from_millis(200));
+        });
+        state.update_repaint_delay(&full_output);
+    }
+    assert!(state.repaint_delay() > Some(Duration::from_millis(150)));
+
+    for _ in event_pump.poll_iter() {}
+    clock.tick(&mut state);
+    let start = Instant::now();
+    let event = clock.wait_for_event(&state, &platform, &mut event_pump);
+    assert!(event.is_none());
+    assert!(start.elapsed() >= Duration::from_millis(150));
+    clock.tick(&mut state);
+
+    let frame_time = clock.last_frame_time().unwrap();
+    assert!(frame_time < 0.1, "{frame_time}");
+    assert!(clock.predicted_dt() < 0.1);
+    assert!(clock.stats().fps > 10.0);
+}
@@ -0,0 +1,103 @@
+//! Drives [`ViewportManager`] with real SDL windows on SDL's dummy video driver.
+//!
+//! SDL can only be initialized from one thread, so everything runs in a single test.
+
+use egui::{Context, ViewportBuilder, ViewportCommand, ViewportId};
+use egui_sdl2_event::viewport::{create_window, ViewportManager};
+use sdl2::event::{Event, WindowEvent};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn close_event(manager: &ViewportManager, id: ViewportId) -> Event {
+    Event::Window {
+        timestamp: 0,
+        window_id: manager.viewport_mut(id).unwrap().window.id(),
+        win_event: WindowEvent::Close,
+    }
+}
+
+#[test]
+fn viewport_manager() {
+    sdl2::hint::set("SDL_VIDEODRIVER", "dummy");
+    let sdl = sdl2::init().unwrap();
+    let video = sdl.video().unwrap();
+    let (window, state) = create_window(
+        &video,
+        &ViewportBuilder::default().with_inner_size([200.0, 100.0]),
+    )
+    .unwrap();
+    let ctx = Context::default();
+    let manager = ViewportManager::new(&ctx, window, state);
+
+    let child = ViewportId::from_hash_of("child");
+    let show_child = Cell::new(true);
+    let cancel_child_close = Arc::new(AtomicBool::new(true));
+    let child_close_requests = Arc::new(AtomicUsize::new(0));
+    let cancel_root_close = Cell::new(true);
+    let repaint_after = Cell::new(None);
+    let run = || {
+        manager.run(&ctx, |ctx| {
+            if let Some(delay) = repaint_after.get() {
+                ctx.request_repaint_after(delay);
+            }
+            if ctx.input(|i| i.viewport().close_requested()) && cancel_root_close.take() {
+                ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            }
+            if !show_child.get() {
+                return;
+            }
+            let cancel = cancel_child_close.clone();
+            let requests = child_close_requests.clone();
+            ctx.show_viewport_deferred(child, ViewportBuilder::default(), move |ctx, _| {
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    if cancel.swap(false, Ordering::Relaxed) {
+                        ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+                    }
+                }
+            });
+        })
+    };
+
+    // a deferred viewport gets a window and a pass of its own:
+    let frames = run();
+    let ids: Vec<_> = frames.iter().map(|frame| frame.viewport_id).collect();
+    assert_eq!(ids, [ViewportId::ROOT, child]);
+    assert!(manager.viewport_ids().contains(&child));
+
+    // the child's close request reaches the child's pass and can be cancelled:
+    manager.handle_event(&close_event(&manager, child));
+    run();
+    assert_eq!(child_close_requests.load(Ordering::Relaxed), 1);
+    assert!(manager.viewport_ids().contains(&child));
+
+    // otherwise the child window is closed, without closing the root:
+    manager.handle_event(&close_event(&manager, child));
+    run();
+    assert_eq!(child_close_requests.load(Ordering::Relaxed), 2);
+    assert!(!manager.viewport_ids().contains(&child));
+    assert!(!manager.should_close());
+    show_child.set(false);
+    run();
+    assert_eq!(manager.viewport_ids(), [ViewportId::ROOT]);
+
+    // the root waits for the repaint requested by the UI:
+    repaint_after.set(Some(Duration::from_secs(10)));
+    run();
+    let delay = manager
+        .viewport_mut(ViewportId::ROOT)
+        .unwrap()
+        .state
+        .repaint_delay();
+    assert!(delay.is_some_and(|delay| delay > Duration::ZERO && delay <= Duration::from_secs(10)));
+
+    // the root closes once a close request is not cancelled:
+    manager.handle_event(&Event::Quit { timestamp: 0 });
+    run();
+    assert!(!manager.should_close());
+    manager.handle_event(&Event::Quit { timestamp: 0 });
+    run();
+    assert!(manager.should_close());
+}